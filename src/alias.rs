@@ -0,0 +1,103 @@
+use crate::config::aliases_directory;
+use crate::error::Error;
+use crate::requirement::Requirement;
+use std::fs::{read, read_dir, remove_file, write};
+
+/// Returns an error if the given name can't be used as an alias.
+///
+/// Alias names must start with a letter, and may only contain letters, digits,
+/// dashes and underscores. Names that are also valid requirements (e.g. `v1`)
+/// aren't allowed, as these would be ambiguous.
+pub fn validate_name(name: &str) -> Result<(), Error> {
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        && Requirement::parse(name).is_err();
+
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::generic(format!(
+            "The alias name {:?} is invalid",
+            name
+        )))
+    }
+}
+
+/// Returns the requirement an alias points to, if the alias exists.
+pub fn get(name: &str) -> Result<Option<String>, Error> {
+    if validate_name(name).is_err() {
+        return Ok(None);
+    }
+
+    let path = aliases_directory()?.join(name);
+
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let data = read(&path).map_err(|e| {
+        Error::generic(format!("Failed to read the alias {}: {}", name, e))
+    })?;
+
+    Ok(Some(String::from_utf8_lossy(&data).trim().to_string()))
+}
+
+/// Creates or updates an alias.
+pub fn set(name: &str, requirement: &Requirement) -> Result<(), Error> {
+    validate_name(name)?;
+
+    write(aliases_directory()?.join(name), requirement.to_string()).map_err(
+        |e| Error::generic(format!("Failed to set the alias {}: {}", name, e)),
+    )
+}
+
+/// Removes an alias, returning `true` if the alias existed.
+pub fn remove(name: &str) -> Result<bool, Error> {
+    if get(name)?.is_none() {
+        return Ok(false);
+    }
+
+    remove_file(aliases_directory()?.join(name)).map_err(|e| {
+        Error::generic(format!("Failed to remove the alias {}: {}", name, e))
+    })?;
+
+    Ok(true)
+}
+
+/// Returns all aliases and the requirements they point to, sorted by name.
+pub fn list() -> Result<Vec<(String, String)>, Error> {
+    let mut aliases = Vec::new();
+
+    for entry in read_dir(aliases_directory()?)? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+
+        if let Some(value) = get(&name)? {
+            aliases.push((name, value));
+        }
+    }
+
+    aliases.sort();
+    Ok(aliases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("team").is_ok());
+        assert!(validate_name("stable-2").is_ok());
+        assert!(validate_name("my_alias").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("latest").is_err());
+        assert!(validate_name("v1").is_err());
+        assert!(validate_name("v1.2.3").is_err());
+        assert!(validate_name("1team").is_err());
+        assert!(validate_name("-team").is_err());
+        assert!(validate_name("te/am").is_err());
+        assert!(validate_name("te.am").is_err());
+    }
+}
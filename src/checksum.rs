@@ -0,0 +1,115 @@
+use crate::error::Error;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::io::{self, Read};
+
+/// A SHA-256 checksum.
+#[derive(PartialEq, Eq, Clone)]
+pub struct Checksum([u8; 32]);
+
+impl Checksum {
+    /// Parses a checksum from a hexadecimal string.
+    ///
+    /// The input may be the contents of a checksum file as produced by
+    /// `sha256sum`, in which case the file name following the checksum is
+    /// ignored.
+    pub fn parse(input: &str) -> Result<Checksum, Error> {
+        let hex = input.split_whitespace().next().unwrap_or("");
+        let invalid = || {
+            Error::generic(format!("The SHA-256 checksum {:?} is invalid", hex))
+        };
+
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+
+        let mut bytes = [0; 32];
+
+        for (index, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16)
+                .map_err(|_| invalid())?;
+        }
+
+        Ok(Checksum(bytes))
+    }
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Checksum({})", self)
+    }
+}
+
+/// A reader that computes the SHA-256 checksum of the data read through it.
+pub struct HashReader<R: Read> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> HashReader<R> {
+    pub fn new(inner: R) -> Self {
+        HashReader {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    /// Reads any remaining data, then returns the checksum of all the data.
+    pub fn finish(mut self) -> io::Result<Checksum> {
+        io::copy(&mut self, &mut io::sink())?;
+        Ok(Checksum(self.hasher.finalize().into()))
+    }
+}
+
+impl<R: Read> Read for HashReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+
+        self.hasher.update(&buf[0..len]);
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HELLO: &str =
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    #[test]
+    fn test_parse() {
+        let sum = Checksum::parse(HELLO).unwrap();
+
+        assert_eq!(sum.to_string(), HELLO);
+        assert_eq!(
+            Checksum::parse(&format!("{}  0.8.0.tar.gz\n", HELLO)),
+            Ok(sum.clone())
+        );
+        assert_eq!(Checksum::parse(&HELLO.to_uppercase()), Ok(sum));
+        assert!(Checksum::parse("").is_err());
+        assert!(Checksum::parse(&HELLO[1..]).is_err());
+        assert!(Checksum::parse(&HELLO.replace('c', "x")).is_err());
+    }
+
+    #[test]
+    fn test_hash_reader() {
+        let mut reader = HashReader::new("hello".as_bytes());
+        let mut buf = [0; 2];
+
+        reader.read_exact(&mut buf).unwrap();
+
+        assert_eq!(&buf, b"he");
+        assert_eq!(reader.finish().unwrap(), Checksum::parse(HELLO).unwrap());
+    }
+}
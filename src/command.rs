@@ -1,10 +1,15 @@
+pub mod alias;
 pub mod clean;
+pub mod current;
 pub mod default;
+pub mod fetch;
 pub mod implode;
 pub mod install;
 pub mod known;
 pub mod list;
+pub mod local;
 pub mod main;
 pub mod remove;
 pub mod run;
 pub mod show;
+pub mod which;
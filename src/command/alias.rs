@@ -0,0 +1,87 @@
+use crate::alias;
+use crate::error::Error;
+use crate::requirement::Requirement;
+use getopts::Options;
+
+const USAGE: &str = "ivm alias [OPTIONS] [COMMAND] [ARGS...]
+
+Manages aliases for versions and version requirements.
+
+An alias can be used in place of a version for commands such as `ivm install`
+and `ivm default`.
+
+Commands:
+
+    set       Creates or updates an alias
+    list      Lists all aliases
+    remove    Removes an alias
+
+Examples:
+
+    ivm alias set team 0.8.0    # Creates the alias `team` for version 0.8.0
+    ivm alias set stable ^0.8   # Creates the alias `stable` for ^0.8
+    ivm alias list              # Lists all aliases
+    ivm alias remove team       # Removes the alias `team`";
+
+pub fn run(arguments: &[String]) -> Result<(), Error> {
+    let mut options = Options::new();
+
+    options.optflag("h", "help", "Shows this help message");
+
+    let matches = options.parse(arguments)?;
+
+    if matches.opt_present("h") {
+        usage!(&options, USAGE);
+        return Ok(());
+    }
+
+    let args = &matches.free;
+
+    match args.first().map(|s| s.as_str()) {
+        Some("set") => {
+            let (name, value) = match (args.get(1), args.get(2)) {
+                (Some(name), Some(value)) => (name, value),
+                _ => {
+                    return Err(Error::generic(
+                        "You must specify an alias name and a version",
+                    ));
+                }
+            };
+
+            let req = Requirement::parse(value)?;
+
+            alias::set(name, &req)?;
+            info!("The alias {} now points to {}", name, req);
+        }
+        Some("list") => {
+            for (name, value) in alias::list()? {
+                println!("{} = {}", name, value);
+            }
+        }
+        Some("remove") => {
+            let name = args.get(1).ok_or_else(|| {
+                Error::generic("You must specify the alias to remove")
+            })?;
+
+            if !alias::remove(name)? {
+                return Err(Error::generic(format!(
+                    "The alias {} doesn't exist",
+                    name
+                )));
+            }
+
+            info!("Removed the alias {}", name);
+        }
+        Some(command) => {
+            return Err(Error::generic(format!(
+                "The command {:?} is not valid",
+                command
+            )));
+        }
+        None => {
+            usage!(&options, USAGE);
+        }
+    }
+
+    Ok(())
+}
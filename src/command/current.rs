@@ -0,0 +1,170 @@
+use crate::config::install_directory;
+use crate::error::Error;
+use crate::resolver::{resolve, Resolved, Source};
+use getopts::Options;
+
+const USAGE: &str = "ivm current [OPTIONS]
+
+Shows the version used in the current directory, and where it's set.
+
+The version is determined using the IVM_VERSION or INKO_VERSION environment
+variable, the nearest .inko-version file, or the default version, in that
+order. If the version isn't installed, or no installed version meets the
+requirement, this command exits with a non-zero exit status.
+
+Examples:
+
+    ivm current           # Shows the active version
+    ivm current --json    # Shows the active version as a JSON object";
+
+pub fn run(arguments: &[String]) -> Result<(), Error> {
+    let mut options = Options::new();
+
+    options.optflag("h", "help", "Shows this help message");
+    options.optflag("", "json", "Prints the output as JSON");
+
+    let matches = options.parse(arguments)?;
+
+    if matches.opt_present("h") {
+        usage!(&options, USAGE);
+        return Ok(());
+    }
+
+    let resolved = resolve()?.ok_or_else(|| {
+        Error::generic(
+            "No version is configured, use `ivm default` or `ivm local` to \
+            set one",
+        )
+    })?;
+
+    let installed = match &resolved.version {
+        Some(version) => {
+            install_directory()?.join(version.to_string()).is_dir()
+        }
+        None => false,
+    };
+
+    if matches.opt_present("json") {
+        println!("{}", to_json(&resolved, installed));
+    } else {
+        match &resolved.version {
+            Some(version) => {
+                println!("{} (set by {})", version, resolved.origin())
+            }
+            None => println!(
+                "{} (set by {})",
+                resolved.requirement,
+                resolved.origin()
+            ),
+        }
+    }
+
+    let version = resolved.installed()?;
+
+    if installed {
+        Ok(())
+    } else {
+        Err(Error::generic(format!(
+            "Version {} is not installed, run `ivm install {}` to install it",
+            version, version
+        )))
+    }
+}
+
+fn to_json(resolved: &Resolved, installed: bool) -> String {
+    let path = resolved
+        .source
+        .path()
+        .map(|p| json_string(&p.to_string_lossy()))
+        .unwrap_or_else(|| "null".to_string());
+    let variable = match resolved.source {
+        Source::Environment(name) => json_string(name),
+        _ => "null".to_string(),
+    };
+    let version = resolved
+        .version
+        .as_ref()
+        .map(|v| json_string(&v.to_string()))
+        .unwrap_or_else(|| "null".to_string());
+    let alias = resolved
+        .alias
+        .as_ref()
+        .map(|a| json_string(a))
+        .unwrap_or_else(|| "null".to_string());
+
+    format!(
+        "{{\"version\":{},\"requirement\":{},\"installed\":{},\
+        \"source\":{},\"path\":{},\"variable\":{},\"alias\":{}}}",
+        version,
+        json_string(&resolved.requirement),
+        installed,
+        json_string(resolved.source.name()),
+        path,
+        variable,
+        alias
+    )
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+
+    out.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                out.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::Version;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_to_json() {
+        let resolved = Resolved {
+            version: Some(Version::new(0, 18, 1)),
+            requirement: "0.18.1".to_string(),
+            source: Source::Project(PathBuf::from(
+                "/a \"b\"/c\\d/e\nf\u{1}/.inko-version",
+            )),
+            alias: Some("te\tam".to_string()),
+        };
+
+        assert_eq!(
+            to_json(&resolved, true),
+            "{\"version\":\"0.18.1\",\"requirement\":\"0.18.1\",\
+            \"installed\":true,\"source\":\"local\",\
+            \"path\":\"/a \\\"b\\\"/c\\\\d/e\\nf\\u0001/.inko-version\",\
+            \"variable\":null,\"alias\":\"te\\tam\"}"
+        );
+
+        let resolved = Resolved {
+            version: None,
+            requirement: "^0.20".to_string(),
+            source: Source::Environment("IVM_VERSION"),
+            alias: None,
+        };
+
+        assert_eq!(
+            to_json(&resolved, false),
+            "{\"version\":null,\"requirement\":\"^0.20\",\"installed\":false,\
+            \"source\":\"environment\",\"path\":null,\
+            \"variable\":\"IVM_VERSION\",\"alias\":null}"
+        );
+    }
+}
@@ -1,24 +1,21 @@
-use crate::config::{
-    bin_directory, default_version_file, install_directory, INKO_EXE,
-};
+use crate::config::{default_version_file, install_directory, INKO_EXE};
 use crate::error::Error;
-use crate::version::Version;
+use crate::requirement::Requirement;
+use crate::shim;
 use getopts::Options;
-use std::fs::{remove_file, write};
-
-#[cfg(unix)]
-use std::os::unix::fs::symlink;
-
-#[cfg(windows)]
-use std::os::windows::fs::symlink_file as symlink;
+use std::fs::write;
 
 const USAGE: &str = "ivm default [OPTIONS] [VERSION]
 
 Sets the default version to use.
 
+If a requirement such as ^0.8 or an alias is given, the highest installed
+version meeting the requirement is used.
+
 Examples:
 
-    ivm default 0.8.0     # Sets the default version to 0.8.0";
+    ivm default 0.8.0     # Sets the default version to 0.8.0
+    ivm default ^0.8      # Sets the default version to the latest 0.8.x version";
 
 pub fn run(arguments: &[String]) -> Result<(), Error> {
     let mut options = Options::new();
@@ -32,13 +29,14 @@ pub fn run(arguments: &[String]) -> Result<(), Error> {
         return Ok(());
     }
 
-    let version = Version::parse(
+    let version = Requirement::parse_or_alias(
         matches
             .free
             .first()
             .map(|s| s.as_str())
             .ok_or_else(|| Error::generic("You must specify a version"))?,
-    )?;
+    )?
+    .select_installed()?;
 
     let version_dir = install_directory()?.join(version.to_string());
 
@@ -55,16 +53,8 @@ pub fn run(arguments: &[String]) -> Result<(), Error> {
         Error::generic(format!("Failed to set the default version: {}", e))
     })?;
 
-    info!("Creating symbolic link for {}", INKO_EXE);
-
-    let sym_bin = bin_directory()?.join(INKO_EXE);
-    let src_bin = version_dir.join("bin").join(INKO_EXE);
-
-    if sym_bin.exists() {
-        remove_file(&sym_bin)?;
-    }
-
-    symlink(src_bin, sym_bin)?;
+    info!("Creating the {} shim", INKO_EXE);
+    shim::install()?;
     info!("The default version is now {}", version);
     Ok(())
 }
@@ -0,0 +1,48 @@
+use crate::command::install::{archive, available};
+use crate::error::Error;
+use crate::settings::{self, Settings};
+use getopts::Options;
+
+const USAGE: &str = "ivm fetch [OPTIONS] VERSION...
+
+Downloads and verifies the source archives of one or more versions, without
+installing them.
+
+This can be used to fill the download cache ahead of time, such as before going
+offline. Installing a fetched version using an exact version number doesn't
+require network access.
+
+Examples:
+
+    ivm fetch 0.8.0          # Downloads version 0.8.0
+    ivm fetch latest ^0.7    # Downloads the latest and latest 0.7.x versions";
+
+pub fn run(arguments: &[String], mut settings: Settings) -> Result<(), Error> {
+    let mut options = Options::new();
+
+    options.optflag("h", "help", "Shows this help message");
+    settings::options(&mut options);
+
+    let matches = options.parse(arguments)?;
+
+    if matches.opt_present("h") {
+        usage!(&options, USAGE);
+        return Ok(());
+    }
+
+    settings.apply(&matches)?;
+
+    if matches.free.is_empty() {
+        return Err(Error::generic("You must specify a version to fetch"));
+    }
+
+    for input in &matches.free {
+        let version = available(Some(input), &settings)?;
+
+        info!("Fetching version {}", version);
+        archive(&version, &settings)?;
+    }
+
+    info!("The versions have been fetched");
+    Ok(())
+}
@@ -1,30 +1,77 @@
+use crate::checksum::{Checksum, HashReader};
 use crate::config::{
-    downloads_directory, install_directory, INKO_EXE, INKO_LIB,
+    downloads_directory, install_directory, manifest_file, INKO_EXE, INKO_LIB,
 };
 use crate::error::Error;
 use crate::http;
-use crate::manifest::Manifest;
+use crate::manifest::{Manifest, Release};
+use crate::progress::Progress;
+use crate::requirement::Requirement;
+use crate::settings::{self, Settings};
+use crate::signature::{self, VerifyReader};
 use crate::version::Version;
 use flate2::read::GzDecoder;
 use getopts::Options;
-use std::fs::{copy, create_dir, create_dir_all, read_dir, remove_dir_all};
+use std::fs::{
+    copy, create_dir, create_dir_all, read, read_dir, read_to_string,
+    remove_dir_all, remove_file, rename, File,
+};
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{self, Command};
 use tar::Archive;
 
 const USAGE: &str = "ivm install [OPTIONS] [VERSION]
 
 Installs a new version.
 
+Instead of downloading a version, the --from option can be used to install a
+version from a local archive or the archive at the given URL. The version is
+read from the archive's Cargo.toml file, unless --as is used.
+
+Instead of an exact version, a requirement such as ^0.8 or \">=0.7, <0.9\" or
+an alias can be given, in which case the highest available version meeting the
+requirement is installed. Versions that are yanked or not part of the stable
+channel are only installed when specified exactly.
+
+Downloaded source archives must have a SHA-256 checksum, either in a .sha256
+file next to the archive or in the list of available versions. The list of
+available versions and the downloaded source archive must also be signed using
+one of the keys configured using the public_key setting, and their signatures
+are verified before they are used. The --insecure option disables signature
+verification and allows archives without a checksum, and should only be used if
+you trust the source of the data.
+
 Examples:
 
     ivm install 0.8.0     # Installs version 0.8.0
-    ivm install latest    # Installs the latest available version";
+    ivm install ^0.8      # Installs the latest available 0.8.x version
+    ivm install latest    # Installs the latest available version
+    ivm install team      # Installs the version the alias `team` points to
+    ivm install --from inko.tar.gz --as 0.8.1 --insecure";
+
+/// The extension of the file containing the checksum of an archive.
+const CHECKSUM_EXT: &str = "sha256";
 
-pub fn run(arguments: &[String]) -> Result<(), Error> {
+/// The extension of the file containing the signature of an archive.
+const SIGNATURE_EXT: &str = "minisig";
+
+pub fn run(arguments: &[String], mut settings: Settings) -> Result<(), Error> {
     let mut options = Options::new();
 
     options.optflag("h", "help", "Shows this help message");
+    settings::options(&mut options);
+    options.optopt(
+        "",
+        "from",
+        "The path or URL of the archive to install",
+        "SOURCE",
+    );
+    options.optopt(
+        "",
+        "as",
+        "The version to install the archive as",
+        "VERSION",
+    );
 
     let matches = options.parse(arguments)?;
 
@@ -33,85 +80,462 @@ pub fn run(arguments: &[String]) -> Result<(), Error> {
         return Ok(());
     }
 
-    Manifest::refresh()?;
+    settings.apply(&matches)?;
 
-    let manifest = Manifest::parse()?;
-    let version = match matches.free.first().map(|s| s.as_str()) {
-        Some("latest") => manifest.latest()?,
-        Some(version) => Version::parse(version)?,
-        None => {
-            return Err(Error::generic(
-                "You must specify a version to install",
-            ));
+    let from = matches.opt_str("from");
+    let (version, source, directory) = match &from {
+        Some(from) => {
+            if !matches.free.is_empty() {
+                return Err(Error::generic(
+                    "A version can't be specified when using --from, use --as \
+                    instead",
+                ));
+            }
+
+            let version = matches
+                .opt_str("as")
+                .map(|v| Version::parse(&v))
+                .transpose()?;
+
+            extract_from(from, version, &settings)?
         }
-    };
+        None => {
+            if matches.opt_present("as") {
+                return Err(Error::generic("--as requires --from"));
+            }
 
-    info!("Downloading version {}", version);
+            let version = available(matches.free.first(), &settings)?;
 
-    let source = extract(&version)?;
+            if !settings.offline {
+                info!("Downloading version {}", version);
+            }
+
+            let source = extract(&version, &settings)?;
+
+            (version, source.clone(), source)
+        }
+    };
     let target = install_directory()?.join(version.to_string());
 
     info!("Installing version {}", version);
 
-    install(&source, &target)?;
+    let result = install(&source, &target);
+
+    // The source code of an archive installed using --from isn't verified, so
+    // we always remove it to ensure it isn't reused by a future installation.
+    if result.is_ok() || from.is_some() {
+        info!("Removing source directory");
 
-    info!("Removing source directory");
+        remove_dir_all(directory).map_err(|error| {
+            Error::generic(format!(
+                "Failed to remove the source directory: {}",
+                error
+            ))
+        })?;
+    }
+
+    result?;
+    info!("Version {} has been installed", version);
+
+    Ok(())
+}
+
+/// Returns the available version to install for the given input.
+pub fn available(
+    input: Option<&String>,
+    settings: &Settings,
+) -> Result<Version, Error> {
+    let manifest = || {
+        Manifest::refresh(settings)?;
+        Manifest::parse()
+    };
+
+    match input.map(|s| s.as_str()) {
+        Some("latest") => manifest()?.latest(),
+        Some(input) => {
+            let req = Requirement::parse_or_alias(input)?;
+
+            // Exact versions don't need the manifest, allowing the use of
+            // downloaded archives without network access.
+            if let Some(version) = req.exact() {
+                return Ok(version);
+            }
 
-    remove_dir_all(source).map_err(|error| {
+            let manifest = manifest()?;
+            let version =
+                req.select(manifest.selectable()).ok_or_else(|| {
+                    Error::generic(format!(
+                        "No available version meets the requirement {}",
+                        req
+                    ))
+                })?;
+
+            info!("Using version {} for the requirement {}", version, req);
+            Ok(version)
+        }
+        None => Err(Error::generic("You must specify a version to install")),
+    }
+}
+
+fn extract(version: &Version, settings: &Settings) -> Result<PathBuf, Error> {
+    let archive = archive(version, settings)?;
+    let downloads = downloads_directory()?;
+    let extract_to = downloads.join(version.to_string());
+    let tmp = downloads.join(format!("extract-{}", process::id()));
+
+    // Source code extracted before may be incomplete or modified (e.g. by a
+    // failed build), so we always extract the verified archive again. The
+    // archive is extracted into a temporary directory first, such that an
+    // interrupted extraction doesn't leave incomplete source code behind.
+    if tmp.exists() {
+        remove_dir_all(&tmp)?;
+    }
+
+    unpack(&archive, &tmp)?;
+
+    if extract_to.exists() {
+        remove_dir_all(&extract_to)?;
+    }
+
+    rename(&tmp, &extract_to).map_err(|error| {
         Error::generic(format!(
-            "Failed to remove the source directory: {}",
+            "Failed to move {} to {}: {}",
+            tmp.display(),
+            extract_to.display(),
             error
         ))
     })?;
 
-    info!("Version {} has been installed", version);
+    Ok(extract_to)
+}
 
-    Ok(())
+/// Returns the path to the verified source archive of the given version,
+/// downloading it if necessary.
+pub fn archive(
+    version: &Version,
+    settings: &Settings,
+) -> Result<PathBuf, Error> {
+    let release = release(version)?;
+    let url = release.as_ref().and_then(|r| r.url.as_deref());
+    let archive = downloads_directory()?.join(format!("{}.tar.gz", version));
+    let name = url
+        .map(|u| http::file_name(u).to_string())
+        .unwrap_or_else(|| format!("{}.tar.gz", version));
+
+    if archive.is_file() {
+        match verify(&archive, &name, release.as_ref(), settings) {
+            Ok(_) => {
+                info!("Using the downloaded archive {}", archive.display())
+            }
+            Err(error) if settings.offline => return Err(error),
+            Err(error) => {
+                info!("{}, downloading the archive again", error);
+                remove_archive(&archive);
+            }
+        }
+    }
+
+    if !archive.is_file() {
+        if settings.offline {
+            return Err(Error::generic(format!(
+                "Version {} isn't downloaded, and can't be downloaded in \
+                offline mode",
+                version
+            )));
+        }
+
+        http::with_mirrors(settings, |base| {
+            let url = match url {
+                Some(url) if url.contains("://") => url.to_string(),
+                Some(path) => {
+                    format!("{}/{}", base, path.trim_start_matches('/'))
+                }
+                None => format!("{}/{}.tar.gz", base, version),
+            };
+
+            download(&url, &archive, release.as_ref(), settings)
+        })?;
+    }
+
+    Ok(archive)
 }
 
-fn extract(version: &Version) -> Result<PathBuf, Error> {
-    let url = &format!("https://releases.inko-lang.org/{}.tar.gz", version);
-    let extract_to = downloads_directory()?.join(version.to_string());
+/// Returns the release of the given version as listed in the downloaded
+/// manifest, if any.
+fn release(version: &Version) -> Result<Option<Release>, Error> {
+    if !manifest_file()?.is_file() {
+        return Ok(None);
+    }
 
-    if extract_to.exists() {
-        return Ok(extract_to);
+    Ok(Manifest::parse()?.release(version).cloned())
+}
+
+/// Downloads and extracts the archive at the given path or URL.
+///
+/// The archive is extracted into a temporary directory, which is returned
+/// along with the version and the directory containing the source code.
+fn extract_from(
+    source: &str,
+    version: Option<Version>,
+    settings: &Settings,
+) -> Result<(Version, PathBuf, PathBuf), Error> {
+    let downloads = downloads_directory()?;
+    let tmp = downloads.join(format!("archive-{}", process::id()));
+    let archive = downloads.join(format!("archive-{}.tar.gz", process::id()));
+
+    if tmp.exists() {
+        remove_dir_all(&tmp)?;
+    }
+
+    info!("Extracting {}", http::redact(source));
+
+    let result = download(source, &archive, None, settings)
+        .and_then(|_| unpack(&archive, &tmp))
+        .and_then(|_| source_root(&tmp))
+        .and_then(|root| {
+            let version = match version {
+                Some(version) => version,
+                None => read(root.join("Cargo.toml"))
+                    .ok()
+                    .and_then(|data| {
+                        Version::from_cargo_toml(&String::from_utf8_lossy(
+                            &data,
+                        ))
+                    })
+                    .ok_or_else(|| {
+                        Error::generic(format!(
+                            "The version of {} couldn't be determined, use \
+                            --as to specify it",
+                            http::redact(source)
+                        ))
+                    })?,
+            };
+
+            Ok((version, root, tmp.clone()))
+        });
+
+    if result.is_err() && tmp.exists() {
+        let _ = remove_dir_all(&tmp);
+    }
+
+    remove_archive(&archive);
+    result
+}
+
+/// Returns the directory containing the source code of an extracted archive.
+///
+/// Archives may contain the source code directly, or wrap it in a single
+/// directory (e.g. `inko-0.8.0/`).
+fn source_root(dir: &Path) -> Result<PathBuf, Error> {
+    if dir.join("Cargo.toml").is_file() {
+        return Ok(dir.to_path_buf());
     }
 
+    let entries = read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+
+    match entries.as_slice() {
+        [entry] if entry.path().is_dir() => Ok(entry.path()),
+        _ => Ok(dir.to_path_buf()),
+    }
+}
+
+/// Downloads and verifies the archive at the given URL.
+///
+/// The archive's checksum and signature are stored next to the archive, such
+/// that the archive can be verified again without network access.
+fn download(
+    url: &str,
+    archive: &Path,
+    release: Option<&Release>,
+    settings: &Settings,
+) -> Result<(), Error> {
     // We don't rely on the manifest to determine if a version exists, as the
     // manifest may be slightly out of date. This in turn would be annoying for
     // users wanting to install a version that just got released.
-    if !http::exists(url) {
-        return Err(Error::generic("The version does not exist"));
+    if !http::exists(url, settings)? {
+        return Err(Error::generic(format!(
+            "{} does not exist",
+            http::redact(url)
+        )));
+    }
+
+    for ext in [CHECKSUM_EXT, SIGNATURE_EXT] {
+        let source = format!("{}.{}", url, ext);
+        let target = with_extension(archive, ext);
+
+        if http::exists(&source, settings)? {
+            http::download(&source, &target, settings)?;
+        } else if target.is_file() {
+            remove_file(&target)?;
+        }
+    }
+
+    http::download(url, archive, settings)?;
+
+    if let Err(error) = verify(archive, http::file_name(url), release, settings)
+    {
+        // If we don't remove the archive, the next installation attempt would
+        // use the invalid archive.
+        remove_archive(archive);
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+/// Verifies the checksum and signature of a downloaded archive.
+///
+/// The name is the name of the archive as published, which the signature must
+/// be produced for. If the release is listed in the manifest, the archive must
+/// also match the size and checksum listed there.
+fn verify(
+    archive: &Path,
+    name: &str,
+    release: Option<&Release>,
+    settings: &Settings,
+) -> Result<(), Error> {
+    let checksum_file = with_extension(archive, CHECKSUM_EXT);
+    let signature_file = with_extension(archive, SIGNATURE_EXT);
+    let mut checksums = Vec::new();
+
+    if checksum_file.is_file() {
+        checksums.push(Checksum::parse(&read_string(&checksum_file)?)?);
     }
 
-    let mut response = http::get(url)?;
+    if let Some(sha256) = release.and_then(|r| r.sha256.as_deref()) {
+        checksums.push(Checksum::parse(sha256)?);
+    }
 
-    create_dir(&extract_to).map_err(|error| {
+    if checksums.is_empty() {
+        if !settings.insecure {
+            return Err(Error::generic(format!(
+                "No checksum is available for {}, use --insecure to skip \
+                checksum verification",
+                archive.display()
+            )));
+        }
+
+        info!("No checksum is available, the archive won't be verified");
+    }
+
+    if let Some(expected) = release.and_then(|r| r.size) {
+        let size = archive.metadata()?.len();
+
+        if size != expected {
+            return Err(Error::generic(format!(
+                "The size of {} is {} bytes, but {} bytes are expected",
+                archive.display(),
+                size,
+                expected
+            )));
+        }
+    }
+
+    let keys = signature::keys(settings)?;
+    let signature = match keys {
+        Some(_) if signature_file.is_file() => {
+            Some(signature::parse(&read_string(&signature_file)?)?)
+        }
+        Some(_) => {
+            return Err(Error::generic(format!(
+                "No signature is available for {}, use --insecure to skip \
+                signature verification",
+                archive.display()
+            )));
+        }
+        None => None,
+    };
+    let verify_error = |error: Error| {
+        Error::generic(format!(
+            "Failed to verify {}: {}",
+            archive.display(),
+            error
+        ))
+    };
+    let file = File::open(archive)?;
+    let mut reader = match (&keys, &signature) {
+        (Some(keys), Some(sig)) => {
+            VerifyReader::new(file, keys, sig, name).map_err(verify_error)?
+        }
+        _ => VerifyReader::unverified(file),
+    };
+    let actual = HashReader::new(&mut reader).finish().map_err(|error| {
+        Error::generic(format!(
+            "Failed to read {}: {}",
+            archive.display(),
+            error
+        ))
+    })?;
+
+    if let Some(expected) = checksums.into_iter().find(|e| e != &actual) {
+        return Err(Error::generic(format!(
+            "The SHA-256 checksum {} of {} doesn't match the expected \
+            checksum {}",
+            actual,
+            archive.display(),
+            expected
+        )));
+    }
+
+    reader.finish().map_err(verify_error)
+}
+
+fn unpack(archive: &Path, into: &Path) -> Result<(), Error> {
+    create_dir(into).map_err(|error| {
         Error::generic(format!(
             "Failed to create {}: {}",
-            extract_to.to_string_lossy(),
+            into.to_string_lossy(),
             error
         ))
     })?;
 
-    Archive::new(GzDecoder::new(response.body_mut().as_reader()))
-        .entries()
-        .and_then(|entries| {
-            for entry in entries {
-                entry.and_then(|mut entry| entry.unpack_in(&extract_to))?;
-            }
+    let mut progress = Progress::files("Extracting");
+    let result = File::open(archive).and_then(|file| {
+        for entry in Archive::new(GzDecoder::new(file)).entries()? {
+            entry.and_then(|mut entry| entry.unpack_in(into))?;
+            progress.advance(1);
+        }
 
-            Ok(())
-        })
-        .map_err(|error| {
-            Error::generic(format!(
-                "Failed to unpack the TAR archive into {}: {}",
-                extract_to.to_string_lossy(),
-                error
-            ))
-        })?;
+        Ok(())
+    });
 
-    Ok(extract_to)
+    progress.finish();
+
+    if let Err(error) = result {
+        // If we don't remove the directory, the next installation attempt
+        // would use the incomplete source code.
+        let _ = remove_dir_all(into);
+
+        return Err(Error::generic(format!(
+            "Failed to unpack {} into {}: {}",
+            archive.to_string_lossy(),
+            into.to_string_lossy(),
+            error
+        )));
+    }
+
+    Ok(())
+}
+
+/// Removes a downloaded archive along with its checksum and signature.
+fn remove_archive(archive: &Path) {
+    let _ = remove_file(archive);
+    let _ = remove_file(with_extension(archive, CHECKSUM_EXT));
+    let _ = remove_file(with_extension(archive, SIGNATURE_EXT));
+}
+
+fn with_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_os_string();
+
+    path.push(".");
+    path.push(extension);
+    PathBuf::from(path)
+}
+
+fn read_string(path: &Path) -> Result<String, Error> {
+    read_to_string(path).map_err(|error| {
+        Error::generic(format!("Failed to read {}: {}", path.display(), error))
+    })
 }
 
 fn install(source: &PathBuf, target: &Path) -> Result<(), Error> {
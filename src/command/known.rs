@@ -1,20 +1,32 @@
 use crate::error::Error;
-use crate::manifest::Manifest;
-use crate::version::Version;
+use crate::manifest::{Manifest, Release};
+use crate::progress::format_bytes;
+use crate::resolver;
+use crate::settings::{self, Settings};
 use getopts::Options;
 
 const USAGE: &str = "ivm known [OPTIONS]
 
 Lists all available versions.
 
+The --long option also shows details about each version, such as its release
+date and the minimum Rust version needed to build it, if these are available.
+
+The list of versions must be signed using one of the keys configured using the
+public_key setting, and the signature is verified before the list is used. The
+--insecure option disables this verification.
+
 Examples:
 
-    ivm known     # Lists all available versions";
+    ivm known          # Lists all available versions
+    ivm known --long   # Lists all available versions and their details";
 
-pub fn run(arguments: &[String]) -> Result<(), Error> {
+pub fn run(arguments: &[String], mut settings: Settings) -> Result<(), Error> {
     let mut options = Options::new();
 
     options.optflag("h", "help", "Shows this help message");
+    options.optflag("l", "long", "Shows details about each version");
+    settings::options(&mut options);
 
     let matches = options.parse(arguments)?;
 
@@ -23,16 +35,57 @@ pub fn run(arguments: &[String]) -> Result<(), Error> {
         return Ok(());
     }
 
-    Manifest::refresh()?;
+    settings.apply(&matches)?;
+
+    Manifest::refresh(&settings)?;
+
+    let active = resolver::active();
+
+    let long = matches.opt_present("l");
+
+    for release in Manifest::parse()?.releases() {
+        let version = &release.version;
+        let mut notes = Vec::new();
+
+        if let Some(active) = active
+            .as_ref()
+            .filter(|a| a.version.as_ref() == Some(version))
+        {
+            notes.push(active.source.name().to_string());
+        }
+
+        if release.yanked {
+            notes.push("yanked".to_string());
+        }
 
-    let default = Version::default();
+        if notes.is_empty() {
+            println!("{}", version);
+        } else {
+            println!("{} ({})", version, notes.join(", "));
+        }
 
-    for version in Manifest::parse()?.iter() {
-        match &default {
-            Some(v) if v == version => println!("{} (default)", version),
-            _ => println!("{}", version),
+        if long {
+            details(release);
         }
     }
 
     Ok(())
 }
+
+fn details(release: &Release) {
+    let size = release.size.map(format_bytes);
+    let fields = [
+        ("date", release.date.as_deref()),
+        ("channel", release.channel.as_deref()),
+        ("rust", release.rust.as_deref()),
+        ("size", size.as_deref()),
+        ("sha256", release.sha256.as_deref()),
+        ("url", release.url.as_deref()),
+    ];
+
+    for (name, value) in fields {
+        if let Some(value) = value {
+            println!("    {}: {}", name, value);
+        }
+    }
+}
@@ -1,8 +1,7 @@
-use crate::config::install_directory;
 use crate::error::Error;
+use crate::resolver;
 use crate::version::Version;
 use getopts::Options;
-use std::fs::read_dir;
 
 const USAGE: &str = "ivm list [OPTIONS]
 
@@ -24,22 +23,13 @@ pub fn run(arguments: &[String]) -> Result<(), Error> {
         return Ok(());
     }
 
-    let mut versions = Vec::new();
-    let default = Version::default();
+    let active = resolver::active();
 
-    for entry in read_dir(install_directory()?)? {
-        if let Ok(version) =
-            Version::parse(&entry?.file_name().to_string_lossy())
-        {
-            versions.push(version);
-        }
-    }
-
-    versions.sort();
-
-    for version in versions {
-        match &default {
-            Some(v) if v == &version => println!("{} (default)", version),
+    for version in Version::installed()? {
+        match &active {
+            Some(a) if a.version.as_ref() == Some(&version) => {
+                println!("{} ({})", version, a.source.name())
+            }
             _ => println!("{}", version),
         }
     }
@@ -0,0 +1,79 @@
+use crate::config::{install_directory, PROJECT_VERSION_FILE};
+use crate::error::Error;
+use crate::requirement::Requirement;
+use crate::shim;
+use getopts::Options;
+use std::env;
+use std::fs::{remove_file, write};
+
+const USAGE: &str = "ivm local [OPTIONS] [VERSION]
+
+Pins the version to use in the current directory.
+
+The version is written to a .inko-version file in the current directory, and
+applies to this directory and all its sub directories. If a requirement such as
+^0.8 or an alias is given, the highest installed version meeting the
+requirement is used.
+
+Examples:
+
+    ivm local 0.8.0      # Uses version 0.8.0 in the current directory
+    ivm local --unset    # Removes the version file from the current directory";
+
+pub fn run(arguments: &[String]) -> Result<(), Error> {
+    let mut options = Options::new();
+
+    options.optflag("h", "help", "Shows this help message");
+    options.optflag("u", "unset", "Removes the version file");
+
+    let matches = options.parse(arguments)?;
+
+    if matches.opt_present("h") {
+        usage!(&options, USAGE);
+        return Ok(());
+    }
+
+    let path = env::current_dir()?.join(PROJECT_VERSION_FILE);
+
+    if matches.opt_present("u") {
+        if !path.is_file() {
+            info!("The current directory doesn't pin a version");
+            return Ok(());
+        }
+
+        info!("Removing {}", path.display());
+
+        return remove_file(&path).map_err(|e| {
+            Error::generic(format!(
+                "Failed to remove {}: {}",
+                path.display(),
+                e
+            ))
+        });
+    }
+
+    let version = Requirement::parse_or_alias(
+        matches
+            .free
+            .first()
+            .map(|s| s.as_str())
+            .ok_or_else(|| Error::generic("You must specify a version"))?,
+    )?
+    .select_installed()?;
+
+    if !install_directory()?.join(version.to_string()).is_dir() {
+        return Err(Error::generic(format!(
+            "The version {} is not installed",
+            version
+        )));
+    }
+
+    write(&path, format!("{}\n", version)).map_err(|e| {
+        Error::generic(format!("Failed to write {}: {}", path.display(), e))
+    })?;
+
+    // The shim may not exist yet if no default version is set.
+    shim::install()?;
+    info!("The local version is now {}", version);
+    Ok(())
+}
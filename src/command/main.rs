@@ -1,17 +1,24 @@
+use crate::command::alias;
 use crate::command::clean;
+use crate::command::current;
 use crate::command::default;
+use crate::command::fetch;
 use crate::command::implode;
 use crate::command::install;
 use crate::command::known;
 use crate::command::list;
+use crate::command::local;
 use crate::command::remove;
 use crate::command::run as run_cmd;
 use crate::command::show;
+use crate::command::which;
 use crate::config::{
-    bin_directory, cache_directory, config_directory, data_directory,
-    downloads_directory, install_directory,
+    aliases_directory, bin_directory, cache_directory, config_directory,
+    data_directory, downloads_directory, install_directory,
 };
 use crate::error::Error;
+use crate::settings::Settings;
+use crate::shim;
 use getopts::{Options, ParsingStyle};
 use std::env;
 use std::fs::create_dir_all;
@@ -24,11 +31,16 @@ Inko versions.
 Commands:
 
     install      Install a new version
+    fetch        Download versions without installing them
     remove       Remove an existing version
     list         List all installed versions
     known        List all available versions
     run          Run a command with a specific version
     default      Set the default version
+    local        Set the version for the current directory
+    alias        Manage version aliases
+    current      Show the active version and where it's set
+    which        Print the path to an executable of a version
     clean        Clean up temporary data
     implode      Removes all versions and temporary data
     show         Prints the value of a setting.
@@ -37,7 +49,11 @@ Examples:
 
     ivm install 0.8.0   # Install version 0.8.0
     ivm remove 0.8.0    # Remove version 0.8.0
-    ivm run 0.8.0 foo   # Run the command `foo` with version 0.8.0";
+    ivm run 0.8.0 foo   # Run the command `foo` with version 0.8.0
+    ivm --offline install 0.8.0  # Install 0.8.0 from a downloaded archive
+
+The --offline option (or setting the IVM_OFFLINE environment variable) makes
+ivm only use data that is already downloaded.";
 
 pub fn run() -> Result<(), Error> {
     let args: Vec<_> = env::args().collect();
@@ -46,6 +62,7 @@ pub fn run() -> Result<(), Error> {
     options.parsing_style(ParsingStyle::StopAtFirstFree);
     options.optflag("h", "help", "Shows this help message");
     options.optflag("v", "version", "Prints the version number");
+    options.optflag("", "offline", "Don't use the network");
 
     let matches = options.parse(&args[1..])?;
 
@@ -71,20 +88,33 @@ pub fn run() -> Result<(), Error> {
     create_dir_all(data_directory()?)?;
     create_dir_all(install_directory()?)?;
     create_dir_all(config_directory()?)?;
+    create_dir_all(aliases_directory()?)?;
     create_dir_all(bin_directory()?)?;
+    shim::migrate()?;
+
+    let mut settings = Settings::load()?;
+
+    if matches.opt_present("offline") {
+        settings.offline = true;
+    }
 
     let cmd_args = &matches.free[1..];
 
     match matches.free.first().map(|s| s.as_str()) {
-        Some("install") => install::run(cmd_args),
+        Some("install") => install::run(cmd_args, settings),
         Some("remove") => remove::run(cmd_args),
         Some("list") => list::run(cmd_args),
-        Some("known") => known::run(cmd_args),
+        Some("fetch") => fetch::run(cmd_args, settings),
+        Some("known") => known::run(cmd_args, settings),
         Some("run") => run_cmd::run(cmd_args),
         Some("default") => default::run(cmd_args),
+        Some("local") => local::run(cmd_args),
+        Some("alias") => alias::run(cmd_args),
+        Some("current") => current::run(cmd_args),
+        Some("which") => which::run(cmd_args),
         Some("clean") => clean::run(cmd_args),
         Some("implode") => implode::run(cmd_args),
-        Some("show") => show::run(cmd_args),
+        Some("show") => show::run(cmd_args, settings),
         Some(command) => Err(Error::generic(format!(
             "The command {:?} is not valid",
             command
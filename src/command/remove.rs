@@ -1,11 +1,11 @@
 use crate::config::{
-    bin_directory, default_version_file, inko_data_directory,
-    install_directory, INKO_EXE,
+    default_version_file, inko_data_directory, install_directory,
 };
 use crate::error::Error;
+use crate::requirement::Requirement;
 use crate::version::Version;
 use getopts::Options;
-use std::fs::{read, remove_dir_all, remove_file};
+use std::fs::{remove_dir_all, remove_file};
 
 const USAGE: &str = "ivm remove [OPTIONS] [VERSION]
 
@@ -14,7 +14,9 @@ Removes an existing version.
 Examples:
 
     ivm remove 0.8.0     # Remove version 0.8.0
-    ivm remove latest    # Remove the latest installed version";
+    ivm remove ~0.8.0    # Remove the latest installed 0.8.x version
+    ivm remove latest    # Remove the latest installed version
+    ivm remove team      # Remove the version the alias `team` points to";
 
 pub fn run(arguments: &[String]) -> Result<(), Error> {
     let mut options = Options::new();
@@ -29,9 +31,9 @@ pub fn run(arguments: &[String]) -> Result<(), Error> {
     }
 
     let version = match matches.free.first().map(|s| s.as_str()) {
-        Some("latest") => Version::latest_installed()
-            .ok_or_else(|| Error::generic("No versions are installed"))?,
-        Some(version) => Version::parse(version)?,
+        Some(input) => {
+            Requirement::parse_or_alias(input)?.select_installed()?
+        }
         None => {
             return Err(Error::generic("You must specify a version to remove"));
         }
@@ -53,14 +55,10 @@ pub fn run(arguments: &[String]) -> Result<(), Error> {
     ];
 
     // If the version we're removing is the default version, also remove the
-    // version file and the corresponding symbolic link.
-    if let Ok(default) = default_version_file() {
-        if let Ok(data) = read(&default) {
-            if String::from_utf8_lossy(&data) == version.to_string() {
-                paths.push(default);
-                paths.push(bin_directory()?.join(INKO_EXE));
-            }
-        }
+    // version file. The shims are left as-is, as they're still used for
+    // project specific versions.
+    if Version::default()?.as_ref() == Some(&version) {
+        paths.push(default_version_file()?);
     }
 
     for path in paths {
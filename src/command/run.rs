@@ -1,6 +1,8 @@
+use crate::alias;
 use crate::config::install_directory;
 use crate::error::Error;
-use crate::version::Version;
+use crate::requirement::Requirement;
+use crate::resolver::resolve;
 use getopts::{Options, ParsingStyle};
 use std::env;
 use std::process::{exit, Command};
@@ -9,16 +11,28 @@ const USAGE: &str = "ivm run [OPTIONS] [VERSION] [COMMAND] [ARGS...]
 
 Runs a command using the given Inko version.
 
+The version can also be a requirement such as ^0.8, in which case the highest
+installed version meeting the requirement is used. Aliases are specified using
+the --alias option, as alias names can't be told apart from commands.
+
+If no version is given, the version set using the IVM_VERSION or INKO_VERSION
+environment variable is used, followed by the version pinned by the nearest
+.inko-version file, and finally the default version.
+
 Examples:
 
     ivm run 0.8.0 inko --version     # Runs `inko --version` using 0.8.0
-    ivm run latest inko --version    # Same, using the latest installed version";
+    ivm run ^0.8 inko --version      # Same, using the latest 0.8.x version
+    ivm run latest inko --version    # Same, using the latest installed version
+    ivm run --alias team inko build  # Runs `inko build` using the team alias
+    ivm run inko --version           # Same, using the active version";
 
 pub fn run(arguments: &[String]) -> Result<(), Error> {
     let mut options = Options::new();
 
     options.parsing_style(ParsingStyle::StopAtFirstFree);
     options.optflag("h", "help", "Shows this help message");
+    options.optopt("", "alias", "The alias of the version to use", "NAME");
 
     let matches = options.parse(arguments)?;
 
@@ -27,11 +41,34 @@ pub fn run(arguments: &[String]) -> Result<(), Error> {
         return Ok(());
     }
 
-    let version = match matches.free.first().map(|s| s.as_str()) {
-        Some("latest") => Version::latest_installed()
-            .ok_or_else(|| Error::generic("No versions are installed"))?,
-        Some(version) => Version::parse(version)?,
-        None => {
+    let alias = matches.opt_str("alias");
+    let first = matches.free.first().map(|s| s.as_str());
+    let (version, cmd_args) = match (alias, first) {
+        (Some(name), _) => {
+            let value = alias::get(&name)?.ok_or_else(|| {
+                Error::generic(format!("The alias {} doesn't exist", name))
+            })?;
+
+            (
+                Requirement::parse(&value)?.select_installed()?,
+                &matches.free[..],
+            )
+        }
+        (None, Some(arg)) if is_requirement(arg) => (
+            Requirement::parse(arg)?.select_installed()?,
+            &matches.free[1..],
+        ),
+        (None, Some(_)) => {
+            let resolved = resolve()?.ok_or_else(|| {
+                Error::generic(
+                    "No version is specified, and no local or default \
+                    version is configured",
+                )
+            })?;
+
+            (resolved.installed()?.clone(), &matches.free[..])
+        }
+        (None, None) => {
             return Err(Error::generic(
                 "You must specify a version to run the command with",
             ));
@@ -47,8 +84,6 @@ pub fn run(arguments: &[String]) -> Result<(), Error> {
         )));
     }
 
-    let cmd_args = &matches.free[1..];
-
     if cmd_args.is_empty() {
         return Err(Error::generic("You must specify a command to run"));
     }
@@ -66,11 +101,22 @@ pub fn run(arguments: &[String]) -> Result<(), Error> {
         ))
     })?;
 
-    let status = Command::new(&matches.free[1])
-        .args(&matches.free[2..])
+    let status = Command::new(&cmd_args[0])
+        .args(&cmd_args[1..])
         .env("PATH", new_path)
         .spawn()?
         .wait()?;
 
     exit(status.code().unwrap_or(0));
 }
+
+/// Returns `true` if the argument looks like a version requirement.
+///
+/// Commands are unlikely to start with a digit or a requirement operator, so if
+/// the argument does we treat it as a requirement.
+fn is_requirement(argument: &str) -> bool {
+    let arg = argument.strip_prefix('v').unwrap_or(argument);
+
+    arg == "latest"
+        || arg.starts_with(|c: char| c.is_ascii_digit() || "=<>~^*".contains(c))
+}
@@ -3,6 +3,8 @@ use crate::config::{
     downloads_directory, install_directory,
 };
 use crate::error::Error;
+use crate::http;
+use crate::settings::Settings;
 use getopts::Options;
 
 const USAGE: &str = "ivm show [OPTIONS] [SETTING]
@@ -16,9 +18,12 @@ Available settings:
     cache        The directory for storing temporary data
     install      The directory containing all installed versions
     config       The directory containing configuration files
-    downloads    The directory containing downloaded files";
+    downloads    The directory containing downloaded files
+    mirror       The mirrors to download from, in the order they're tried
+    proxy        The proxies to use and the hosts to not use them for
+    tls          The CA bundle and client certificate to use";
 
-pub fn run(arguments: &[String]) -> Result<(), Error> {
+pub fn run(arguments: &[String], settings: Settings) -> Result<(), Error> {
     let mut options = Options::new();
 
     options.optflag("h", "help", "Shows this help message");
@@ -49,6 +54,39 @@ pub fn run(arguments: &[String]) -> Result<(), Error> {
         Some("downloads") => {
             println!("{}", downloads_directory()?.to_string_lossy());
         }
+        Some("mirror") => {
+            for mirror in settings.mirrors() {
+                println!("{}", http::redact(mirror));
+            }
+        }
+        Some("proxy") => {
+            let proxies = &settings.proxies;
+
+            for (name, proxy) in
+                [("https", &proxies.https), ("http", &proxies.http)]
+            {
+                match proxy {
+                    Some(url) => println!("{}: {}", name, http::redact(url)),
+                    None => println!("{}: none", name),
+                }
+            }
+
+            println!("no_proxy: {}", proxies.no_proxy.join(","));
+        }
+        Some("tls") => {
+            let paths = [
+                ("ca_bundle", &settings.ca_bundle),
+                ("client_certificate", &settings.client_certificate),
+                ("client_key", &settings.client_key),
+            ];
+
+            for (name, path) in paths {
+                match path {
+                    Some(path) => println!("{}: {}", name, path.display()),
+                    None => println!("{}: none", name),
+                }
+            }
+        }
         Some(setting) => {
             return Err(Error::generic(format!(
                 "The setting {} doesn't exist",
@@ -0,0 +1,78 @@
+use crate::config::{install_directory, INKO_EXE};
+use crate::error::Error;
+use crate::requirement::Requirement;
+use crate::resolver::resolve;
+use getopts::Options;
+
+const USAGE: &str = "ivm which [OPTIONS] [TOOL]
+
+Prints the absolute path to an executable of an installed version.
+
+If no tool is given, the path to the inko executable is printed. If no version
+is given, the active version for the current directory is used.
+
+Examples:
+
+    ivm which                     # Prints the path to inko of the active version
+    ivm which --version 0.8.0     # Prints the path to inko of version 0.8.0
+    ivm which --version ^0.8      # Same, using the latest 0.8.x version";
+
+pub fn run(arguments: &[String]) -> Result<(), Error> {
+    let mut options = Options::new();
+
+    options.optflag("h", "help", "Shows this help message");
+    options.optopt(
+        "",
+        "version",
+        "The version, requirement or alias to use",
+        "VERSION",
+    );
+
+    let matches = options.parse(arguments)?;
+
+    if matches.opt_present("h") {
+        usage!(&options, USAGE);
+        return Ok(());
+    }
+
+    let version = match matches.opt_str("version") {
+        Some(input) => Requirement::parse_or_alias(&input)?.installed()?,
+        None => {
+            let resolved = resolve()?.ok_or_else(|| {
+                Error::generic(
+                    "No version is specified, and no version is configured \
+                    for the current directory",
+                )
+            })?;
+
+            resolved.installed()?.clone()
+        }
+    };
+
+    let tool = matches.free.first().map(|s| s.as_str()).unwrap_or(INKO_EXE);
+
+    if tool.is_empty() || tool.contains(['/', '\\']) || tool == ".." {
+        return Err(Error::generic(format!("The tool {:?} is invalid", tool)));
+    }
+
+    let bin_dir = install_directory()?.join(version.to_string()).join("bin");
+
+    if !bin_dir.is_dir() {
+        return Err(Error::generic(format!(
+            "Version {} is not installed",
+            version
+        )));
+    }
+
+    let path = bin_dir.join(tool);
+
+    if !path.is_file() {
+        return Err(Error::generic(format!(
+            "Version {} doesn't provide the executable {:?}",
+            version, tool
+        )));
+    }
+
+    println!("{}", path.display());
+    Ok(())
+}
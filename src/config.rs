@@ -4,6 +4,11 @@ use std::path::PathBuf;
 
 pub const INKO_EXE: &str = "inko";
 pub const INKO_LIB: &str = "libinko.a";
+pub const PROJECT_VERSION_FILE: &str = ".inko-version";
+
+/// The environment variables that override the version to use, in order of
+/// priority.
+pub const VERSION_VARIABLES: [&str; 2] = ["IVM_VERSION", "INKO_VERSION"];
 
 const BASE_DIR: &str = "ivm";
 
@@ -82,6 +87,18 @@ pub fn default_version_file() -> Result<PathBuf, Error> {
     config_directory().map(|d| d.join("version"))
 }
 
+pub fn aliases_directory() -> Result<PathBuf, Error> {
+    config_directory().map(|d| d.join("aliases"))
+}
+
 pub fn manifest_file() -> Result<PathBuf, Error> {
     downloads_directory().map(|d| d.join("manifest.txt"))
 }
+
+pub fn config_file() -> Result<PathBuf, Error> {
+    config_directory().map(|d| d.join("config"))
+}
+
+pub fn manifest_headers_file() -> Result<PathBuf, Error> {
+    downloads_directory().map(|d| d.join("manifest.headers"))
+}
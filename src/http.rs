@@ -1,27 +1,1128 @@
 use crate::error::Error;
+use crate::progress::{Progress, ProgressReader};
+use crate::settings::{self, Settings};
+use std::collections::hash_map::RandomState;
+use std::ffi::OsString;
+use std::fs::{
+    copy, read, read_to_string, remove_file, rename, write, File, OpenOptions,
+};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError};
+use std::thread;
 use std::time::Duration;
 use ureq::http::Response;
-use ureq::{self, Agent, Body};
+use ureq::tls::{
+    parse_pem, Certificate, ClientCert, PemItem, PrivateKey, RootCerts,
+    TlsConfig,
+};
+use ureq::{self, Agent, Body, Proxy};
 
-const TIMEOUT: u64 = 10;
+/// The size of the chunks to read response bodies in.
+const CHUNK_SIZE: usize = 64 * 1024;
 
-pub fn get(url: &str) -> Result<Response<Body>, Error> {
-    let agent = agent();
+/// The time to wait before retrying a failed request for the first time, in
+/// milliseconds.
+const BACKOFF: u64 = 500;
 
-    match agent.get(url).call() {
-        Ok(response) => Ok(response),
-        Err(err) => Err(Error::generic(format!("GET {} failed: {}", url, err))),
+/// The maximum time to wait before retrying a failed request, in milliseconds.
+const MAX_BACKOFF: u64 = 30_000;
+
+/// Returns a reader for the data at the given URL.
+///
+/// The URL can be an HTTP(S) URL, a `file://` URL, or a path to a local file.
+pub fn get(url: &str, settings: &Settings) -> Result<Box<dyn Read>, Error> {
+    if let Some(path) = local_path(url) {
+        return match File::open(&path) {
+            Ok(file) => Ok(Box::new(file)),
+            Err(err) => Err(Error::generic(format!(
+                "Failed to open {}: {}",
+                path.display(),
+                err
+            ))),
+        };
+    }
+
+    match request("GET", url, settings, &[]) {
+        Ok(response) => Ok(body(response, settings)),
+        Err(err) => Err(Error::generic(format!(
+            "GET {} failed: {}",
+            redact(url),
+            err
+        ))),
+    }
+}
+
+/// Returns the data at the given URL as a `String`.
+pub fn get_string(url: &str, settings: &Settings) -> Result<String, Error> {
+    let mut body = String::new();
+
+    get(url, settings)?
+        .read_to_string(&mut body)
+        .map_err(|err| {
+            Error::generic(format!("Failed to read {}: {}", redact(url), err))
+        })?;
+
+    Ok(body)
+}
+
+/// Values used for determining if the data at a URL has changed.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Validators {
+    /// The value of the `ETag` header.
+    pub etag: Option<String>,
+
+    /// The value of the `Last-Modified` header.
+    pub last_modified: Option<String>,
+}
+
+/// The result of requesting data that may not have changed.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Changed {
+    /// The data changed, and is returned along with its new validators.
+    Yes(String, Validators),
+
+    /// The data didn't change.
+    No,
+
+    /// The data doesn't exist.
+    Missing,
+}
+
+/// Returns the data at the given URL as a `String`, unless it didn't change
+/// according to the given validators.
+pub fn get_if_changed(
+    url: &str,
+    validators: &Validators,
+    settings: &Settings,
+) -> Result<Changed, Error> {
+    if let Some(path) = local_path(url) {
+        if !path.is_file() {
+            return Ok(Changed::Missing);
+        }
+
+        let data = get_string(url, settings)?;
+
+        return Ok(Changed::Yes(data, Validators::default()));
+    }
+
+    let mut headers = Vec::new();
+
+    if let Some(etag) = &validators.etag {
+        headers.push(("If-None-Match", etag.as_str()));
+    }
+
+    if let Some(date) = &validators.last_modified {
+        headers.push(("If-Modified-Since", date.as_str()));
+    }
+
+    let response = match request("GET", url, settings, &headers) {
+        Ok(response) if response.status() == 304 => return Ok(Changed::No),
+        Ok(response) => response,
+        Err(ureq::Error::StatusCode(404 | 410)) => return Ok(Changed::Missing),
+        Err(err) => {
+            return Err(Error::generic(format!(
+                "GET {} failed: {}",
+                redact(url),
+                err
+            )))
+        }
+    };
+
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+    let validators = Validators {
+        etag: header("ETag"),
+        last_modified: header("Last-Modified"),
+    };
+    let mut data = String::new();
+
+    body(response, settings)
+        .read_to_string(&mut data)
+        .map_err(|err| {
+            Error::generic(format!("Failed to read {}: {}", redact(url), err))
+        })?;
+
+    Ok(Changed::Yes(data, validators))
+}
+
+/// Returns `true` if the data at the given URL exists.
+///
+/// An error is returned if this couldn't be determined, such as when the
+/// server can't be reached.
+pub fn exists(url: &str, settings: &Settings) -> Result<bool, Error> {
+    if let Some(path) = local_path(url) {
+        return Ok(path.is_file());
+    }
+
+    match request("HEAD", url, settings, &[]) {
+        Ok(_) => Ok(true),
+        Err(ureq::Error::StatusCode(404 | 410)) => Ok(false),
+        Err(err) => Err(Error::generic(format!(
+            "HEAD {} failed: {}",
+            redact(url),
+            err
+        ))),
+    }
+}
+
+/// Downloads the data at the given URL to a file.
+///
+/// The data is first written to a file with the `.part` extension, which is
+/// renamed once the download completes. If this file already exists (e.g.
+/// because a previous download got interrupted), the download resumes where
+/// it left off.
+///
+/// To ensure the partial data belongs to the same version of the file, the
+/// ETag or Last-Modified header of the response is stored in a file with the
+/// `.validator` extension. A download is only resumed if this file exists, and
+/// its value is sent using the `If-Range` header.
+pub fn download(
+    url: &str,
+    path: &Path,
+    settings: &Settings,
+) -> Result<(), Error> {
+    let part = with_suffix(path, ".part");
+    let validator_file = with_suffix(path, ".validator");
+    let write_error = |err: io::Error| {
+        Error::generic(format!(
+            "Failed to write to {}: {}",
+            part.display(),
+            err
+        ))
+    };
+
+    if let Some(source) = local_path(url) {
+        copy(&source, &part).map_err(|err| {
+            Error::generic(format!(
+                "Failed to copy {}: {}",
+                source.display(),
+                err
+            ))
+        })?;
+
+        return rename(&part, path).map_err(write_error);
+    }
+
+    let agent = agent(settings, url).map_err(|err| {
+        Error::generic(format!("GET {} failed: {}", redact(url), err))
+    })?;
+
+    // Failing to send the request and failing to receive the body use the
+    // same number of attempts, such that a download isn't retried more often
+    // than a regular request.
+    let mut attempt = 1;
+
+    loop {
+        let validator = read_to_string(&validator_file).ok();
+        let offset = match validator {
+            Some(_) => part.metadata().map(|m| m.len()).unwrap_or(0),
+            None => 0,
+        };
+        let range = format!("bytes={}-", offset);
+        let headers = match &validator {
+            Some(value) if offset > 0 => {
+                vec![("Range", range.as_str()), ("If-Range", value.as_str())]
+            }
+            _ => Vec::new(),
+        };
+        let response = match send(&agent, "GET", url, settings, &headers) {
+            Ok(response) => response,
+            // The partial data is invalid (e.g. because the file changed), so
+            // we have to start over.
+            Err(ureq::Error::StatusCode(416)) if offset > 0 => {
+                remove_file(&validator_file).map_err(write_error)?;
+                continue;
+            }
+            Err(err) if attempt < settings.attempts && is_transient(&err) => {
+                wait(attempt, &format!("GET {} failed: {}", redact(url), err));
+                attempt += 1;
+                continue;
+            }
+            Err(err) => {
+                return Err(Error::generic(format!(
+                    "GET {} failed: {}",
+                    redact(url),
+                    err
+                )));
+            }
+        };
+
+        // If the server doesn't support range requests or the file changed,
+        // it sends the entire body and we have to overwrite the partial data.
+        let resume = response.status() == 206;
+
+        if resume {
+            info!(
+                "Resuming the download of {} at {} bytes",
+                redact(url),
+                offset
+            );
+        }
+
+        let start = if resume { offset } else { 0 };
+        let total = response.body().content_length().map(|len| start + len);
+        let name = file_name(url);
+        let progress =
+            Progress::bytes(&format!("Downloading {}", name), start, total);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(resume)
+            .truncate(!resume)
+            .open(&part)
+            .map_err(write_error)?;
+
+        if !resume {
+            match validator_of(&response) {
+                Some(value) => write(&validator_file, value),
+                None if validator_file.is_file() => {
+                    remove_file(&validator_file)
+                }
+                None => Ok(()),
+            }
+            .map_err(write_error)?;
+        }
+
+        let mut reader =
+            ProgressReader::new(body(response, settings), progress);
+        let mut buf = vec![0; CHUNK_SIZE];
+        let result = loop {
+            match reader.read(&mut buf) {
+                Ok(0) => break Ok(()),
+                Ok(len) => {
+                    // Errors produced when writing the data (e.g. because the
+                    // disk is full) won't be resolved by retrying.
+                    if let Err(err) = file.write_all(&buf[0..len]) {
+                        reader.finish();
+                        return Err(write_error(err));
+                    }
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => break Err(err),
+            }
+        };
+
+        reader.finish();
+
+        match result {
+            Ok(_) => break,
+            Err(err) if attempt < settings.attempts => {
+                wait(
+                    attempt,
+                    &format!("Failed to download {}: {}", redact(url), err),
+                );
+                attempt += 1;
+            }
+            Err(err) => {
+                return Err(Error::generic(format!(
+                    "Failed to download {}: {}",
+                    redact(url),
+                    err
+                )));
+            }
+        }
+    }
+
+    rename(&part, path).map_err(write_error)?;
+
+    if validator_file.is_file() {
+        remove_file(&validator_file).map_err(write_error)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the value to use for the `If-Range` header when resuming the
+/// download of a response.
+///
+/// Weak ETags can't be used for range requests, in which case the value of the
+/// Last-Modified header is used instead.
+fn validator_of(response: &Response<Body>) -> Option<&str> {
+    let header =
+        |name| response.headers().get(name).and_then(|v| v.to_str().ok());
+
+    header("ETag")
+        .filter(|v| !v.starts_with("W/"))
+        .or_else(|| header("Last-Modified"))
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = OsString::from(path);
+
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Calls the closure with the base URL of each mirror, until it succeeds.
+///
+/// If all mirrors fail, the error produced for the last mirror is returned.
+pub fn with_mirrors<T, F: FnMut(&str) -> Result<T, Error>>(
+    settings: &Settings,
+    mut func: F,
+) -> Result<T, Error> {
+    let mirrors = settings.mirrors();
+    let (last, rest) = mirrors.split_last().expect("no mirrors are defined");
+
+    for base in rest {
+        match func(base) {
+            Ok(value) => return Ok(value),
+            Err(err) => info!("{}, trying the next mirror", err),
+        }
     }
+
+    func(last)
 }
 
-pub fn exists(url: &str) -> bool {
-    agent().head(url).call().is_ok()
+/// Sends a request, retrying it if it fails due to a transient error.
+fn request(
+    method: &str,
+    url: &str,
+    settings: &Settings,
+    headers: &[(&str, &str)],
+) -> Result<Response<Body>, ureq::Error> {
+    let agent = agent(settings, url)?;
+    let mut attempt = 1;
+
+    loop {
+        match send(&agent, method, url, settings, headers) {
+            Err(err) if attempt < settings.attempts && is_transient(&err) => {
+                wait(
+                    attempt,
+                    &format!("{} {} failed: {}", method, redact(url), err),
+                );
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Sends a request once.
+fn send(
+    agent: &Agent,
+    method: &str,
+    url: &str,
+    settings: &Settings,
+    headers: &[(&str, &str)],
+) -> Result<Response<Body>, ureq::Error> {
+    let mut builder = if method == "HEAD" {
+        agent.head(url)
+    } else {
+        agent.get(url)
+    };
+
+    for &(name, value) in headers {
+        builder = builder.header(name, value);
+    }
+
+    if let Some(value) = authorization(url, settings) {
+        builder = builder.header("Authorization", value);
+    }
+
+    builder.call()
+}
+
+/// Waits before the next attempt, after the given number of failed attempts.
+fn wait(attempt: u32, message: &str) {
+    let delay = backoff(attempt);
+
+    info!(
+        "{}, retrying in {:.1} seconds",
+        message,
+        delay.as_secs_f64()
+    );
+    thread::sleep(delay);
+}
+
+/// Returns `true` if a request may succeed when retried.
+fn is_transient(error: &ureq::Error) -> bool {
+    match error {
+        ureq::Error::StatusCode(code) => {
+            matches!(code, 408 | 429) || (500..600).contains(code)
+        }
+        ureq::Error::Io(_)
+        | ureq::Error::Timeout(_)
+        | ureq::Error::HostNotFound
+        | ureq::Error::ConnectionFailed
+        | ureq::Error::BodyStalled => true,
+        _ => false,
+    }
+}
+
+/// Returns the time to wait before the next attempt, after the given number
+/// of failed attempts.
+///
+/// The time grows exponentially, and is randomized to prevent many clients
+/// from retrying at the same time.
+fn backoff(attempt: u32) -> Duration {
+    let max = BACKOFF
+        .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(MAX_BACKOFF);
+    let jitter = RandomState::new().build_hasher().finish() % (max / 2 + 1);
+
+    Duration::from_millis(max / 2 + jitter)
+}
+
+/// Returns a reader for the body of a response.
+fn body(response: Response<Body>, settings: &Settings) -> Box<dyn Read> {
+    let reader = response.into_body().into_reader();
+
+    match settings.timeouts.read {
+        Some(timeout) => Box::new(IdleReader::new(reader, timeout)),
+        None => Box::new(reader),
+    }
 }
 
-fn agent() -> Agent {
-    Agent::config_builder()
-        .timeout_global(Some(Duration::from_secs(TIMEOUT)))
+/// Returns the name of the file a URL points to, excluding any query string or
+/// fragment.
+pub fn file_name(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Returns the value of the `Authorization` header to send for the URL, if
+/// any.
+///
+/// The token is only sent to the configured mirrors, while logins from the
+/// netrc file are sent to the hosts they're defined for. The default login of
+/// the netrc file is only sent to the configured mirrors.
+fn authorization(url: &str, settings: &Settings) -> Option<String> {
+    let (_, rest) = url.split_once("://")?;
+
+    // ureq already uses the credentials included in the URL, if any.
+    if userinfo(url).is_some() {
+        return None;
+    }
+
+    let host = settings::host(rest);
+    let mirror = settings.is_mirror_host(&host);
+
+    if let Some(token) = settings.token.as_ref().filter(|_| mirror) {
+        return Some(format!("Bearer {}", token));
+    }
+
+    let netrc = &settings.netrc;
+    let login = netrc
+        .login(&host)
+        .or_else(|| netrc.default_login().filter(|_| mirror));
+
+    login.map(|login| {
+        let pair = format!("{}:{}", login.login, login.password);
+
+        format!("Basic {}", base64(pair.as_bytes()))
+    })
+}
+
+/// Returns the URL with any credentials it contains replaced, such that it's
+/// safe to include in messages.
+pub fn redact(url: &str) -> String {
+    match userinfo(url) {
+        Some(info) => url.replacen(&format!("{}@", info), "***@", 1),
+        None => url.to_string(),
+    }
+}
+
+/// Returns the credentials included in a URL.
+fn userinfo(url: &str) -> Option<&str> {
+    let (_, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+
+    authority.rsplit_once('@').map(|(info, _)| info)
+}
+
+fn base64(input: &[u8]) -> String {
+    const CHARS: &[u8] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut output = String::new();
+
+    for chunk in input.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let value = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output
+                    .push(CHARS[(value >> (18 - i * 6)) as usize & 63] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+
+    output
+}
+
+fn agent(settings: &Settings, url: &str) -> Result<Agent, ureq::Error> {
+    if settings.offline {
+        return Err(ureq::Error::Io(io::Error::other(
+            "network requests aren't allowed in offline mode",
+        )));
+    }
+
+    let timeouts = &settings.timeouts;
+
+    // ureq reads the proxy from the environment by default, but doesn't
+    // support NO_PROXY, so we always set the proxy explicitly.
+    let proxy = settings.proxies.for_url(url).map(Proxy::new).transpose()?;
+
+    Ok(Agent::config_builder()
+        .timeout_connect(timeouts.connect)
+        .timeout_recv_response(timeouts.read)
+        // When a read times out, the thread used by IdleReader remains blocked
+        // until the read completes. Limiting the time receiving the body may
+        // take ensures such reads complete, but we can't use a limit shorter
+        // than the total timeout without breaking slow downloads.
+        .timeout_recv_body(timeouts.total)
+        .timeout_global(timeouts.total)
         .user_agent(format!("ivm {}", env!("CARGO_PKG_VERSION")))
+        .proxy(proxy)
+        .tls_config(tls_config(settings)?)
         .build()
-        .into()
+        .into())
+}
+
+fn tls_config(settings: &Settings) -> Result<TlsConfig, ureq::Error> {
+    let mut config = TlsConfig::builder();
+
+    if let Some(path) = &settings.ca_bundle {
+        let certs = certificates(&read_pem(path)?)?;
+
+        if certs.is_empty() {
+            return Err(ureq::Error::Io(io::Error::other(format!(
+                "the CA bundle {} doesn't contain any certificates",
+                path.display()
+            ))));
+        }
+
+        config = config.root_certs(RootCerts::new_with_certs(&certs));
+    }
+
+    if let Some(path) = &settings.client_certificate {
+        let pem = read_pem(path)?;
+        let certs = certificates(&pem)?;
+        let key = match &settings.client_key {
+            Some(path) => PrivateKey::from_pem(&read_pem(path)?)?,
+            None => PrivateKey::from_pem(&pem)?,
+        };
+
+        config =
+            config.client_cert(Some(ClientCert::new_with_certs(&certs, key)));
+    }
+
+    Ok(config.build())
+}
+
+fn certificates(pem: &[u8]) -> Result<Vec<Certificate<'static>>, ureq::Error> {
+    let mut certs = Vec::new();
+
+    for item in parse_pem(pem) {
+        if let PemItem::Certificate(cert) = item? {
+            certs.push(cert);
+        }
+    }
+
+    Ok(certs)
+}
+
+fn read_pem(path: &Path) -> Result<Vec<u8>, ureq::Error> {
+    read(path).map_err(|e| {
+        ureq::Error::Io(io::Error::new(
+            e.kind(),
+            format!("failed to read {}: {}", path.display(), e),
+        ))
+    })
+}
+
+/// A reader that fails if no data arrives within a certain amount of time.
+///
+/// Blocking reads can't be interrupted, so the underlying reader is read from
+/// in a separate thread. If a read times out, this thread is left behind until
+/// the underlying reader returns, which happens at the latest when the total
+/// timeout expires (if any).
+struct IdleReader {
+    receiver: Receiver<io::Result<Vec<u8>>>,
+    timeout: Duration,
+    buffer: Vec<u8>,
+    offset: usize,
+    done: bool,
+}
+
+impl IdleReader {
+    fn new<R: Read + Send + 'static>(mut inner: R, timeout: Duration) -> Self {
+        let (sender, receiver) = sync_channel(4);
+
+        thread::spawn(move || loop {
+            let mut buf = vec![0; CHUNK_SIZE];
+            let result = inner.read(&mut buf).map(|len| {
+                buf.truncate(len);
+                buf
+            });
+            let stop = !matches!(&result, Ok(buf) if !buf.is_empty());
+
+            if sender.send(result).is_err() || stop {
+                break;
+            }
+        });
+
+        IdleReader {
+            receiver,
+            timeout,
+            buffer: Vec::new(),
+            offset: 0,
+            done: false,
+        }
+    }
+}
+
+impl Read for IdleReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.offset == self.buffer.len() {
+            if self.done {
+                return Ok(0);
+            }
+
+            match self.receiver.recv_timeout(self.timeout) {
+                Ok(Ok(data)) if data.is_empty() => {
+                    self.done = true;
+                    return Ok(0);
+                }
+                Ok(Ok(data)) => {
+                    self.buffer = data;
+                    self.offset = 0;
+                }
+                Ok(Err(err)) => return Err(err),
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!(
+                            "no data received for {} seconds",
+                            self.timeout.as_secs()
+                        ),
+                    ));
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+            }
+        }
+
+        let data = &self.buffer[self.offset..];
+        let len = data.len().min(buf.len());
+
+        buf[0..len].copy_from_slice(&data[0..len]);
+        self.offset += len;
+        Ok(len)
+    }
+}
+
+/// Returns the path to a local file, if the URL refers to one.
+///
+/// URLs using the `file` scheme and URLs without a scheme (i.e. plain paths)
+/// are treated as local files.
+fn local_path(url: &str) -> Option<PathBuf> {
+    if let Some(path) = url.strip_prefix("file://") {
+        // file://localhost/foo is the same as file:///foo.
+        let path = path.strip_prefix("localhost").unwrap_or(path);
+
+        Some(PathBuf::from(path))
+    } else if url.contains("://") {
+        None
+    } else {
+        Some(PathBuf::from(url))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::netrc::Netrc;
+    use std::env::temp_dir;
+    use std::fs::{create_dir_all, remove_dir_all};
+    use std::net::TcpListener;
+    use std::process;
+    use std::sync::{Arc, Mutex};
+
+    /// Starts an HTTP server that sends the given responses in order, returning
+    /// its base URL and the (lowercase) requests it receives.
+    pub(crate) fn server(
+        responses: Vec<&'static str>,
+    ) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = requests.clone();
+
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+
+                while !request.ends_with(b"\r\n\r\n") {
+                    match stream.read(&mut buf).unwrap() {
+                        0 => break,
+                        len => request.extend_from_slice(&buf[0..len]),
+                    }
+                }
+
+                received
+                    .lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&request).to_lowercase());
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        (url, requests)
+    }
+
+    /// Resumes the download of a file of which "hello " is downloaded,
+    /// returning the downloaded data and the requests sent.
+    fn resume(
+        name: &str,
+        validator: Option<&str>,
+        responses: Vec<&'static str>,
+    ) -> (String, Vec<String>) {
+        let dir = temp_dir().join(format!("ivm-{}-{}", name, process::id()));
+        let path = dir.join("inko.tar.gz");
+        let part = with_suffix(&path, ".part");
+        let validator_file = with_suffix(&path, ".validator");
+
+        create_dir_all(&dir).unwrap();
+        write(&part, "hello ").unwrap();
+
+        if let Some(value) = validator {
+            write(&validator_file, value).unwrap();
+        }
+
+        let (base, requests) = server(responses);
+
+        download(
+            &format!("{}/inko.tar.gz", base),
+            &path,
+            &Settings::default(),
+        )
+        .unwrap();
+
+        assert!(!part.exists());
+        assert!(!validator_file.exists());
+
+        let data = read_to_string(&path).unwrap();
+
+        remove_dir_all(&dir).unwrap();
+
+        let requests = requests.lock().unwrap().clone();
+
+        (data, requests)
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("https://a.com/x/0.1.0.tar.gz"), "0.1.0.tar.gz");
+        assert_eq!(file_name("https://a.com/x.tar.gz?a=/b#c"), "x.tar.gz");
+        assert_eq!(file_name("inko.tar.gz"), "inko.tar.gz");
+    }
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(redact("https://a.com/x"), "https://a.com/x");
+        assert_eq!(redact("https://u:p@a.com/x"), "https://***@a.com/x");
+        assert_eq!(redact("https://u@a.com"), "https://***@a.com");
+        assert_eq!(redact("https://a.com/x@y"), "https://a.com/x@y");
+        assert_eq!(redact("/tmp/a@b"), "/tmp/a@b");
+    }
+
+    #[test]
+    fn test_authorization() {
+        let settings = Settings {
+            mirrors: vec!["https://private.com/inko".to_string()],
+            token: Some("secret".to_string()),
+            netrc: Netrc::parse(
+                "machine other.com login alice password hunter2",
+            ),
+            ..Settings::default()
+        };
+
+        assert_eq!(
+            authorization("https://private.com/inko/manifest.txt", &settings),
+            Some("Bearer secret".to_string())
+        );
+        assert_eq!(
+            authorization("https://other.com/foo", &settings),
+            Some("Basic YWxpY2U6aHVudGVyMg==".to_string())
+        );
+        assert_eq!(authorization("https://u:p@private.com/", &settings), None);
+        assert_eq!(authorization("https://public.com/", &settings), None);
+        assert_eq!(authorization("/tmp/foo", &settings), None);
+
+        let settings = Settings {
+            mirrors: vec!["https://private.com/inko".to_string()],
+            netrc: Netrc::parse("default login bob password hunter2"),
+            ..Settings::default()
+        };
+
+        assert_eq!(
+            authorization("https://private.com/inko/manifest.txt", &settings),
+            Some("Basic Ym9iOmh1bnRlcjI=".to_string())
+        );
+        assert_eq!(authorization("https://public.com/", &settings), None);
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foob"), "Zm9vYg==");
+    }
+
+    #[test]
+    fn test_local_path() {
+        assert_eq!(local_path("https://example.com/foo"), None);
+        assert_eq!(local_path("http://example.com/foo"), None);
+        assert_eq!(
+            local_path("file:///foo/bar"),
+            Some(PathBuf::from("/foo/bar"))
+        );
+        assert_eq!(
+            local_path("file://localhost/foo"),
+            Some(PathBuf::from("/foo"))
+        );
+        assert_eq!(local_path("/foo/bar"), Some(PathBuf::from("/foo/bar")));
+        assert_eq!(local_path("foo/bar"), Some(PathBuf::from("foo/bar")));
+    }
+
+    #[test]
+    fn test_local_files() {
+        let dir = temp_dir().join(format!("ivm-http-{}", std::process::id()));
+        let file = dir.join("manifest.txt");
+        let url = format!("file://{}", file.display());
+
+        create_dir_all(&dir).unwrap();
+        write(&file, "0.1.0\n").unwrap();
+
+        let settings = Settings::default();
+
+        assert_eq!(exists(&url, &settings), Ok(true));
+        assert_eq!(exists(&file.to_string_lossy(), &settings), Ok(true));
+        assert_eq!(exists(&format!("{}.minisig", url), &settings), Ok(false));
+        assert_eq!(get_string(&url, &settings).unwrap(), "0.1.0\n");
+        assert!(get(&format!("{}.minisig", url), &settings).is_err());
+
+        let target = dir.join("copy.txt");
+
+        download(&url, &target, &settings).unwrap();
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "0.1.0\n");
+        assert!(!dir.join("copy.txt.part").exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_is_transient() {
+        assert!(is_transient(&ureq::Error::StatusCode(500)));
+        assert!(is_transient(&ureq::Error::StatusCode(503)));
+        assert!(is_transient(&ureq::Error::StatusCode(429)));
+        assert!(is_transient(&ureq::Error::ConnectionFailed));
+        assert!(is_transient(&ureq::Error::HostNotFound));
+        assert!(is_transient(&ureq::Error::Io(
+            io::ErrorKind::ConnectionReset.into()
+        )));
+        assert!(!is_transient(&ureq::Error::StatusCode(404)));
+        assert!(!is_transient(&ureq::Error::StatusCode(403)));
+        assert!(!is_transient(&ureq::Error::BadUri("foo".to_string())));
+    }
+
+    #[test]
+    fn test_backoff() {
+        for _ in 0..10 {
+            let first = backoff(1).as_millis() as u64;
+            let third = backoff(3).as_millis() as u64;
+            let last = backoff(100).as_millis() as u64;
+
+            assert!((BACKOFF / 2..=BACKOFF).contains(&first));
+            assert!((BACKOFF * 2..=BACKOFF * 4).contains(&third));
+            assert!((MAX_BACKOFF / 2..=MAX_BACKOFF).contains(&last));
+        }
+    }
+
+    #[test]
+    fn test_idle_reader() {
+        let data = vec![1; CHUNK_SIZE + 10];
+        let mut reader = IdleReader::new(
+            io::Cursor::new(data.clone()),
+            Duration::from_secs(5),
+        );
+        let mut output = Vec::new();
+
+        reader.read_to_end(&mut output).unwrap();
+        assert_eq!(output, data);
+        assert_eq!(reader.read(&mut [0; 4]).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_idle_reader_timeout() {
+        struct Slow;
+
+        impl Read for Slow {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                thread::sleep(Duration::from_secs(1));
+                Ok(0)
+            }
+        }
+
+        let mut reader = IdleReader::new(Slow, Duration::from_millis(10));
+        let error = reader.read(&mut [0; 4]).unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn test_download_resume() {
+        let (data, requests) = resume(
+            "download-resume",
+            Some("\"abc\""),
+            vec![
+                "HTTP/1.1 206 Partial Content\r\nContent-Length: 5\r\n\
+                Content-Range: bytes 6-10/11\r\nConnection: close\r\n\r\n\
+                world",
+            ],
+        );
+
+        assert_eq!(data, "hello world");
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].contains("\r\nrange: bytes=6-\r\n"));
+        assert!(requests[0].contains("\r\nif-range: \"abc\"\r\n"));
+    }
+
+    #[test]
+    fn test_download_resume_with_changed_file() {
+        let (data, requests) = resume(
+            "download-changed",
+            Some("\"abc\""),
+            vec![
+                "HTTP/1.1 200 OK\r\nContent-Length: 5\r\nETag: \"def\"\r\n\
+                Connection: close\r\n\r\nworld",
+            ],
+        );
+
+        assert_eq!(data, "world");
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].contains("\r\nrange: bytes=6-\r\n"));
+    }
+
+    #[test]
+    fn test_download_resume_with_invalid_range() {
+        let (data, requests) = resume(
+            "download-invalid-range",
+            Some("Wed, 21 Oct 2015 07:28:00 GMT"),
+            vec![
+                "HTTP/1.1 416 Range Not Satisfiable\r\nContent-Length: 0\r\n\
+                Connection: close\r\n\r\n",
+                "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\
+                Connection: close\r\n\r\nworld",
+            ],
+        );
+
+        assert_eq!(data, "world");
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0]
+            .contains("\r\nif-range: wed, 21 oct 2015 07:28:00 gmt\r\n"));
+        assert!(!requests[1].contains("range:"));
+    }
+
+    #[test]
+    fn test_download_resume_without_validator() {
+        let (data, requests) = resume(
+            "download-without-validator",
+            None,
+            vec![
+                "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\
+                Connection: close\r\n\r\nworld",
+            ],
+        );
+
+        assert_eq!(data, "world");
+        assert!(!requests[0].contains("range:"));
+    }
+
+    #[test]
+    fn test_download_attempts() {
+        let dir = temp_dir().join(format!("ivm-attempts-{}", process::id()));
+        let path = dir.join("inko.tar.gz");
+        let settings = Settings {
+            attempts: 2,
+            ..Settings::default()
+        };
+        let (base, requests) = server(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\
+            Connection: close\r\n\r\nworld",
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\
+            Connection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\
+            Connection: close\r\n\r\nworld",
+        ]);
+
+        create_dir_all(&dir).unwrap();
+
+        let url = format!("{}/inko.tar.gz", base);
+
+        assert!(download(&url, &path, &settings).is_err());
+        assert_eq!(requests.lock().unwrap().len(), 2);
+
+        remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_get_if_changed() {
+        let (base, requests) = server(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 6\r\nETag: \"abc\"\r\n\
+            Last-Modified: Wed, 21 Oct 2015 07:28:00 GMT\r\n\
+            Connection: close\r\n\r\n0.1.0\n",
+            "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\
+            Connection: close\r\n\r\n",
+        ]);
+        let url = format!("{}/manifest.txt", base);
+        let settings = Settings::default();
+        let validators = Validators {
+            etag: Some("\"abc\"".to_string()),
+            last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
+        };
+
+        assert_eq!(
+            get_if_changed(&url, &Validators::default(), &settings),
+            Ok(Changed::Yes("0.1.0\n".to_string(), validators.clone()))
+        );
+        assert_eq!(
+            get_if_changed(&url, &validators, &settings),
+            Ok(Changed::No)
+        );
+        assert_eq!(
+            get_if_changed(&url, &validators, &settings),
+            Ok(Changed::Missing)
+        );
+
+        let requests = requests.lock().unwrap();
+
+        assert!(!requests[0].contains("if-none-match"));
+        assert!(!requests[0].contains("if-modified-since"));
+        assert!(requests[1].contains("\r\nif-none-match: \"abc\"\r\n"));
+        assert!(requests[1]
+            .contains("\r\nif-modified-since: wed, 21 oct 2015 07:28:00 gmt"));
+    }
 }
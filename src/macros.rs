@@ -23,6 +23,15 @@ macro_rules! info {
     };
 }
 
+macro_rules! warn {
+    ($message:expr $(,$arg:expr)*) => {
+        eprintln!(
+            "\x1b[1m\x1b[33mwarning:\x1b[0m\x1b[0m {}",
+            format!($message $(,$arg)*)
+        )
+    };
+}
+
 macro_rules! error {
     ($message:expr $(,$arg:expr)*) => {
         eprintln!(
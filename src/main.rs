@@ -1,18 +1,33 @@
 // This must come first so other modules can use the macros.
 mod macros;
 
+mod alias;
+mod checksum;
 mod command;
 mod config;
 mod error;
 mod http;
 mod manifest;
+mod netrc;
+mod progress;
+mod requirement;
+mod resolver;
+mod settings;
+mod shim;
+mod signature;
 mod version;
 
 use command::main;
 use std::process::exit;
 
 fn main() {
-    if let Err(e) = main::run() {
+    let result = if shim::invoked() {
+        shim::run()
+    } else {
+        main::run()
+    };
+
+    if let Err(e) = result {
         error!("{}", e);
         exit(1);
     }
@@ -1,21 +1,155 @@
-use crate::config::manifest_file;
+use crate::config::{manifest_file, manifest_headers_file};
 use crate::error::Error;
-use crate::http;
+use crate::http::{self, Changed, Validators};
+use crate::settings::Settings;
+use crate::signature;
 use crate::version::Version;
-use std::fs::File;
+use std::fmt;
+use std::fs::{read_to_string, write, File};
 use std::io::{Read, Write};
+use std::path::Path;
 use std::time::{Duration, SystemTime};
 
-const URL: &str = "https://releases.inko-lang.org/manifest.txt";
-const EXPIRE_AFTER: u64 = 6 * 60 * 60;
+/// The name of the manifest in the legacy format.
+const NAME: &str = "manifest.txt";
+
+/// The name of the manifest in the version 2 format.
+///
+/// This manifest uses a separate file, such that older versions of ivm that
+/// only support the legacy format keep working.
+const V2_NAME: &str = "manifest-v2.txt";
+
+/// The time after which to check for a new manifest, in seconds.
+///
+/// Checking is cheap if the manifest didn't change, as the server then only
+/// responds with a "304 Not Modified" response.
+const EXPIRE_AFTER: u64 = 15 * 60;
+
+/// The maximum time refreshing the manifest may take, in seconds.
+const DEADLINE: u64 = 10;
+
+/// The latest version of the manifest format that is supported.
+const FORMAT: u32 = 2;
+
+/// The name of the default release channel.
+pub const STABLE: &str = "stable";
+
+/// A release listed in the manifest.
+///
+/// Manifests in the legacy format only list versions, in which case all other
+/// fields are empty.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Release {
+    pub version: Version,
+
+    /// The date the version was released on, such as `2024-05-01`.
+    pub date: Option<String>,
+
+    /// The URL of the source archive, either absolute or relative to the
+    /// mirror.
+    pub url: Option<String>,
+
+    /// The size of the source archive in bytes.
+    pub size: Option<u64>,
+
+    /// The SHA-256 checksum of the source archive.
+    pub sha256: Option<String>,
+
+    /// If set to `true`, the release is withdrawn and shouldn't be installed
+    /// unless explicitly requested.
+    pub yanked: bool,
+
+    /// The release channel, such as "stable" or "nightly".
+    pub channel: Option<String>,
+
+    /// The minimum Rust version needed to build the release.
+    pub rust: Option<String>,
+}
+
+impl Release {
+    pub fn new(version: Version) -> Release {
+        Release {
+            version,
+            date: None,
+            url: None,
+            size: None,
+            sha256: None,
+            yanked: false,
+            channel: None,
+            rust: None,
+        }
+    }
+
+    /// Parses a line in the version 2 format, such as:
+    ///
+    /// ```text
+    /// 0.9.0 date=2024-05-01 channel=stable rust=1.78 size=1024 sha256=...
+    /// ```
+    ///
+    /// The version is followed by `KEY=VALUE` pairs and optionally the `yanked`
+    /// flag. Unknown keys are ignored, such that new fields can be added
+    /// without breaking older versions of ivm.
+    fn parse(line: &str) -> Result<Release, String> {
+        let mut words = line.split_whitespace();
+        let version = words.next().unwrap_or("");
+        let mut release =
+            Release::new(Version::parse(version).map_err(|e| e.to_string())?);
+
+        for word in words {
+            let (key, value) = match word.split_once('=') {
+                Some((key, value)) => (key, value),
+                None if word == "yanked" => {
+                    release.yanked = true;
+                    continue;
+                }
+                None => continue,
+            };
+
+            match key {
+                "date" => release.date = Some(value.to_string()),
+                "url" => release.url = Some(value.to_string()),
+                "size" => {
+                    release.size = Some(value.parse().map_err(|_| {
+                        format!("the size {:?} is invalid", value)
+                    })?);
+                }
+                "sha256" => {
+                    if value.len() != 64
+                        || !value.bytes().all(|b| b.is_ascii_hexdigit())
+                    {
+                        return Err(format!(
+                            "the SHA-256 checksum {:?} is invalid",
+                            value
+                        ));
+                    }
+
+                    release.sha256 = Some(value.to_lowercase());
+                }
+                "yanked" => release.yanked = value == "true",
+                "channel" => release.channel = Some(value.to_string()),
+                "rust" => release.rust = Some(value.to_string()),
+                _ => {}
+            }
+        }
+
+        Ok(release)
+    }
+
+    /// Returns `true` if the release may be selected when installing the
+    /// latest version or a version meeting a requirement.
+    pub fn is_selectable(&self) -> bool {
+        !self.yanked && self.channel.as_deref().map_or(true, |c| c == STABLE)
+    }
+}
 
 pub struct Manifest {
-    versions: Vec<Version>,
+    releases: Vec<Release>,
 }
 
 impl Manifest {
-    pub fn new(versions: Vec<Version>) -> Self {
-        Manifest { versions }
+    pub fn new(mut releases: Vec<Release>) -> Self {
+        releases.sort_by(|a, b| a.version.cmp(&b.version));
+        Manifest { releases }
     }
 
     pub fn parse() -> Result<Self, Error> {
@@ -31,20 +165,84 @@ impl Manifest {
                 ))
             })?;
 
-        let mut versions = Vec::new();
+        Self::parse_str(&content)
+    }
+
+    /// Parses a manifest in either the legacy or version 2 format.
+    ///
+    /// The legacy format consists of one version per line. The version 2
+    /// format starts with the line `manifest 2`, followed by one release per
+    /// line. Mirrors may only provide the legacy format, so the downloaded
+    /// manifest may use either format. Empty lines and lines starting with a
+    /// `#` are ignored.
+    pub fn parse_str(input: &str) -> Result<Self, Error> {
+        let mut lines = input
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, l)| !l.is_empty() && !l.starts_with('#'))
+            .peekable();
+        let format = match lines.peek() {
+            Some((line, header)) if header.starts_with("manifest") => {
+                let format = header
+                    .strip_prefix("manifest")
+                    .and_then(|v| v.trim().parse::<u32>().ok())
+                    .ok_or_else(|| {
+                        Error::generic(format!(
+                            "Line {} of the manifest is invalid: the header \
+                            {:?} is invalid",
+                            line, header
+                        ))
+                    })?;
 
-        for line in content.lines() {
-            versions.push(Version::parse(line)?);
-        }
+                if format > FORMAT {
+                    return Err(Error::generic(format!(
+                        "The manifest uses format {}, but only formats up to \
+                        {} are supported, please upgrade ivm",
+                        format, FORMAT
+                    )));
+                }
+
+                lines.next();
+                format
+            }
+            _ => 1,
+        };
+        let mut releases = Vec::new();
+
+        for (line, input) in lines {
+            let release = if format == 1 {
+                Version::parse(input).map(Release::new)
+            } else {
+                Release::parse(input).map_err(Error::generic)
+            }
+            .map_err(|e| {
+                Error::generic(format!(
+                    "Line {} of the manifest is invalid: {}",
+                    line, e
+                ))
+            })?;
 
-        versions.sort();
+            releases.push(release);
+        }
 
-        Ok(Self::new(versions))
+        Ok(Self::new(releases))
     }
 
-    pub fn refresh() -> Result<(), Error> {
+    pub fn refresh(settings: &Settings) -> Result<(), Error> {
         let file = manifest_file()?;
 
+        if settings.offline {
+            return if file.exists() {
+                Ok(())
+            } else {
+                Err(Error::generic(
+                    "No manifest is downloaded, and it can't be downloaded \
+                    in offline mode",
+                ))
+            };
+        }
+
         // To reduce the amount of HTTP requests, we only update the manifest
         // if deemed necessary.
         let download = if file.exists() {
@@ -58,16 +256,130 @@ impl Manifest {
         };
 
         if download {
-            let mut response = http::get(URL)?;
-            let body = response.body_mut().read_to_string().map_err(|e| {
+            update(&file, &manifest_headers_file()?, settings)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn releases(&self) -> impl Iterator<Item = &Release> {
+        self.releases.iter()
+    }
+
+    /// Returns the release of the given version, if it's listed.
+    pub fn release(&self, version: &Version) -> Option<&Release> {
+        self.releases.iter().find(|r| &r.version == version)
+    }
+
+    /// Returns the versions that may be selected when installing a version
+    /// meeting a requirement, excluding yanked and non-stable releases.
+    pub fn selectable(&self) -> impl DoubleEndedIterator<Item = &Version> {
+        self.releases
+            .iter()
+            .filter(|r| r.is_selectable())
+            .map(|r| &r.version)
+    }
+
+    /// Returns the latest version, excluding pre-releases, yanked releases and
+    /// releases not in the stable channel.
+    pub fn latest(&self) -> Result<Version, Error> {
+        self.selectable()
+            .rev()
+            .find(|v| !v.is_prerelease())
+            .cloned()
+            .ok_or_else(|| Error::generic("There are no versions available"))
+    }
+}
+
+/// Downloads the manifest into the given file if it changed, storing the state
+/// needed for checking if it changed in the given headers file.
+fn update(
+    file: &Path,
+    headers: &Path,
+    settings: &Settings,
+) -> Result<(), Error> {
+    // The manifest is small, so we don't want to wait for it as long as we
+    // would for a source archive.
+    let settings = &Settings {
+        timeouts: settings
+            .timeouts
+            .with_deadline(Duration::from_secs(DEADLINE)),
+        ..settings.clone()
+    };
+    let state = read_state(headers);
+    let result = http::with_mirrors(settings, |base| {
+        // Mirrors may not provide the manifest in the version 2 format, in
+        // which case we fall back to the legacy format.
+        for name in [V2_NAME, NAME] {
+            let url = format!("{}/{}", base, name);
+
+            // Validators are specific to the server that produced them, so
+            // we only use them for the same URL. The URL is stored without
+            // any credentials it may contain.
+            let validators = if file.exists()
+                && state.url.as_deref() == Some(&http::redact(&url))
+            {
+                state.validators.clone()
+            } else {
+                Validators::default()
+            };
+
+            match http::get_if_changed(&url, &validators, settings)? {
+                Changed::Yes(body, validators) => {
+                    let new = verify(
+                        &url,
+                        name,
+                        &body,
+                        validators,
+                        state.timestamp,
+                        settings,
+                    )?;
+
+                    return Ok(Some((url, body, new)));
+                }
+                Changed::No => return Ok(None),
+                Changed::Missing => continue,
+            }
+        }
+
+        Err(Error::generic(format!(
+            "The mirror {} doesn't provide a manifest",
+            http::redact(base)
+        )))
+    })?;
+
+    match result {
+        Some((url, body, new)) => {
+            File::create(file)
+                .and_then(|mut handle| handle.write_all(body.as_bytes()))
+                .map_err(|error| {
+                    Error::generic(format!(
+                        "Failed to update the manifest file: {}",
+                        error
+                    ))
+                })?;
+
+            let new = State {
+                url: Some(http::redact(&url)),
+                timestamp: new.timestamp.or(state.timestamp),
+                ..new
+            };
+
+            write(headers, new.to_string()).map_err(|error| {
                 Error::generic(format!(
-                    "failed to read the response body: {}",
-                    e
+                    "Failed to update {}: {}",
+                    headers.display(),
+                    error
                 ))
             })?;
-
-            File::create(&file)
-                .and_then(|mut handle| handle.write_all(body.as_bytes()))
+        }
+        None => {
+            // The manifest didn't change, so we only need to reset the time
+            // after which it expires.
+            File::options()
+                .append(true)
+                .open(file)
+                .and_then(|f| f.set_modified(SystemTime::now()))
                 .map_err(|error| {
                     Error::generic(format!(
                         "Failed to update the manifest file: {}",
@@ -75,32 +387,318 @@ impl Manifest {
                     ))
                 })?;
         }
+    }
 
-        Ok(())
+    Ok(())
+}
+
+/// Verifies the signature of a downloaded manifest, returning the state to
+/// store alongside it.
+///
+/// The name is the name of the manifest file, which the signature must be
+/// produced for.
+///
+/// If the manifest is signed, the timestamp of its signature must not be older
+/// than the given timestamp. This prevents a server from serving an older
+/// (but validly signed) manifest, such as one that lacks security releases.
+fn verify(
+    url: &str,
+    name: &str,
+    body: &str,
+    validators: Validators,
+    timestamp: Option<u64>,
+    settings: &Settings,
+) -> Result<State, Error> {
+    let mut state = State {
+        url: None,
+        validators,
+        timestamp: None,
+    };
+
+    if let Some(keys) = signature::keys(settings)? {
+        let verify_error = |e: Error| {
+            Error::generic(format!(
+                "Failed to verify {}: {}",
+                http::redact(url),
+                e
+            ))
+        };
+        let sig = signature::download(url, settings)?;
+
+        keys.verify(body.as_bytes(), &sig, name)
+            .map_err(verify_error)?;
+
+        let signed = signature::trusted_field(&sig, "timestamp")
+            .and_then(|v| v.parse::<u64>().ok())
+            .ok_or_else(|| {
+                verify_error(Error::generic(
+                    "The trusted comment of the signature doesn't include a \
+                    timestamp",
+                ))
+            })?;
+
+        if timestamp.is_some_and(|t| signed < t) {
+            return Err(verify_error(Error::generic(
+                "The manifest is older than the manifest downloaded before",
+            )));
+        }
+
+        state.timestamp = Some(signed);
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = &Version> {
-        self.versions.iter()
+    Ok(state)
+}
+
+/// The state of the downloaded manifest.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct State {
+    /// The URL the manifest is downloaded from, without any credentials.
+    url: Option<String>,
+
+    /// The validators to use for checking if the manifest changed.
+    validators: Validators,
+
+    /// The timestamp of the signature of the most recent manifest.
+    timestamp: Option<u64>,
+}
+
+impl State {
+    fn parse(input: &str) -> State {
+        let mut state = State::default();
+
+        for (key, value) in input
+            .lines()
+            .filter_map(|l| l.split_once('='))
+            .map(|(k, v)| (k.trim(), v.trim().to_string()))
+        {
+            match key {
+                "url" => state.url = Some(value),
+                "etag" => state.validators.etag = Some(value),
+                "last_modified" => state.validators.last_modified = Some(value),
+                "timestamp" => state.timestamp = value.parse().ok(),
+                _ => {}
+            }
+        }
+
+        state
     }
+}
 
-    pub fn latest(&self) -> Result<Version, Error> {
-        self.versions
-            .last()
-            .cloned()
-            .ok_or_else(|| Error::generic("There are no versions available"))
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fields = [
+            ("url", self.url.clone()),
+            ("etag", self.validators.etag.clone()),
+            ("last_modified", self.validators.last_modified.clone()),
+            ("timestamp", self.timestamp.map(|v| v.to_string())),
+        ];
+
+        for (key, value) in fields {
+            if let Some(value) = value {
+                writeln!(f, "{} = {}", key, value)?;
+            }
+        }
+
+        Ok(())
     }
 }
 
+/// Reads the state of the downloaded manifest, if any.
+fn read_state(path: &Path) -> State {
+    read_to_string(path)
+        .map(|v| State::parse(&v))
+        .unwrap_or_default()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::http::tests::server;
+    use std::env;
+    use std::fs::{create_dir_all, remove_dir_all};
+    use std::process;
+
+    fn manifest(versions: Vec<Version>) -> Manifest {
+        Manifest::new(versions.into_iter().map(Release::new).collect())
+    }
 
     #[test]
     fn test_latest() {
-        let manifest1 = Manifest::new(vec![Version::new(1, 0, 0)]);
-        let manifest2 = Manifest::new(Vec::new());
+        let manifest1 = manifest(vec![Version::new(1, 0, 0)]);
+        let manifest2 = manifest(Vec::new());
+        let manifest3 = manifest(vec![
+            Version::new(1, 0, 0),
+            Version::parse("1.1.0-rc.1").unwrap(),
+        ]);
+        let manifest4 = Manifest::parse_str(
+            "manifest 2\n1.0.0\n1.1.0 yanked\n1.2.0 channel=nightly",
+        )
+        .unwrap();
 
         assert_eq!(manifest1.latest(), Ok(Version::new(1, 0, 0)));
         assert!(manifest2.latest().is_err());
+        assert_eq!(manifest3.latest(), Ok(Version::new(1, 0, 0)));
+        assert_eq!(manifest4.latest(), Ok(Version::new(1, 0, 0)));
+        assert_eq!(
+            manifest4.selectable().collect::<Vec<_>>(),
+            vec![&Version::new(1, 0, 0)]
+        );
+    }
+
+    #[test]
+    fn test_parse_legacy() {
+        let manifest = Manifest::parse_str("0.2.0\n\n0.1.0\n").unwrap();
+
+        assert_eq!(
+            manifest.releases().collect::<Vec<_>>(),
+            vec![
+                &Release::new(Version::new(0, 1, 0)),
+                &Release::new(Version::new(0, 2, 0))
+            ]
+        );
+        assert!(Manifest::parse_str("0.1.0\nfoo").is_err());
+    }
+
+    #[test]
+    fn test_parse_v2() {
+        let sha = "A".repeat(64);
+        let input = format!(
+            "# A comment
+manifest 2
+0.2.0 date=2024-05-01 url=https://a/0.2.0.tar.gz size=1024 sha256={} \
+channel=stable rust=1.78 future=1
+0.1.0 yanked
+",
+            sha
+        );
+        let manifest = Manifest::parse_str(&input).unwrap();
+        let releases = manifest.releases().collect::<Vec<_>>();
+
+        assert_eq!(
+            releases,
+            vec![
+                &Release {
+                    yanked: true,
+                    ..Release::new(Version::new(0, 1, 0))
+                },
+                &Release {
+                    version: Version::new(0, 2, 0),
+                    date: Some("2024-05-01".to_string()),
+                    url: Some("https://a/0.2.0.tar.gz".to_string()),
+                    size: Some(1024),
+                    sha256: Some("a".repeat(64)),
+                    yanked: false,
+                    channel: Some("stable".to_string()),
+                    rust: Some("1.78".to_string()),
+                }
+            ]
+        );
+        assert_eq!(
+            manifest
+                .release(&Version::new(0, 2, 0))
+                .and_then(|r| r.size),
+            Some(1024)
+        );
+        assert_eq!(manifest.release(&Version::new(0, 3, 0)), None);
+        assert!(Manifest::parse_str("manifest 2\n0.1.0 size=foo").is_err());
+        assert!(Manifest::parse_str("manifest 2\n0.1.0 sha256=abc").is_err());
+        assert!(Manifest::parse_str("manifest 3\n0.1.0").is_err());
+        assert!(Manifest::parse_str("manifest foo\n0.1.0").is_err());
+    }
+
+    #[test]
+    fn test_state() {
+        let state = State {
+            url: Some("https://example.com/manifest.txt".to_string()),
+            validators: Validators {
+                etag: Some("\"a=b\"".to_string()),
+                last_modified: Some(
+                    "Wed, 21 Oct 2015 07:28:00 GMT".to_string(),
+                ),
+            },
+            timestamp: Some(42),
+        };
+
+        assert_eq!(State::parse(&state.to_string()), state);
+        assert_eq!(
+            State::parse(&State::default().to_string()),
+            State::default()
+        );
+        assert_eq!(
+            State::parse("etag = foo\ntimestamp = bar"),
+            State {
+                validators: Validators {
+                    etag: Some("foo".to_string()),
+                    last_modified: None
+                },
+                ..State::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_update() {
+        let dir = env::temp_dir().join(format!("ivm-update-{}", process::id()));
+        let file = dir.join("manifest.txt");
+        let headers = dir.join("manifest.headers");
+        let not_found = "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\
+            Connection: close\r\n\r\n";
+        let (base, requests) = server(vec![
+            not_found,
+            "HTTP/1.1 200 OK\r\nContent-Length: 6\r\nETag: \"abc\"\r\n\
+            Connection: close\r\n\r\n0.1.0\n",
+            not_found,
+            "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n",
+        ]);
+        let settings = Settings {
+            insecure: true,
+            mirrors: vec![base.clone()],
+            ..Settings::default()
+        };
+
+        create_dir_all(&dir).unwrap();
+        update(&file, &headers, &settings).unwrap();
+
+        assert_eq!(read_to_string(&file).unwrap(), "0.1.0\n");
+        assert_eq!(
+            read_state(&headers),
+            State {
+                url: Some(format!("{}/{}", base, NAME)),
+                validators: Validators {
+                    etag: Some("\"abc\"".to_string()),
+                    last_modified: None
+                },
+                timestamp: None
+            }
+        );
+
+        let expired = SystemTime::now() - Duration::from_secs(EXPIRE_AFTER);
+
+        File::options()
+            .append(true)
+            .open(&file)
+            .and_then(|f| f.set_modified(expired))
+            .unwrap();
+        update(&file, &headers, &settings).unwrap();
+
+        assert_eq!(read_to_string(&file).unwrap(), "0.1.0\n");
+        assert!(file.metadata().unwrap().modified().unwrap() > expired);
+
+        let requests = requests.lock().unwrap();
+        let lines: Vec<_> =
+            requests.iter().map(|r| r.lines().next().unwrap()).collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "get /manifest-v2.txt http/1.1",
+                "get /manifest.txt http/1.1",
+                "get /manifest-v2.txt http/1.1",
+                "get /manifest.txt http/1.1",
+            ]
+        );
+        assert!(requests[3].contains("\r\nif-none-match: \"abc\"\r\n"));
+
+        remove_dir_all(&dir).unwrap();
     }
 }
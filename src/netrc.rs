@@ -0,0 +1,174 @@
+use crate::error::Error;
+use std::env;
+use std::fs::read_to_string;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+/// The environment variable to use for overwriting the path to the netrc
+/// file.
+const NETRC_VARIABLE: &str = "NETRC";
+
+/// A login for a machine.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Login {
+    pub login: String,
+    pub password: String,
+}
+
+/// The logins found in a netrc file.
+///
+/// Only the `machine`, `default`, `login` and `password` tokens are used, other
+/// tokens (including macro definitions) are ignored.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Netrc {
+    machines: Vec<(String, Login)>,
+    default: Option<Login>,
+}
+
+impl Netrc {
+    /// Loads the netrc file of the current user, if it exists.
+    pub fn load() -> Result<Netrc, Error> {
+        let Some(path) = env::var_os(NETRC_VARIABLE)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| {
+                env::var_os("HOME").map(|h| PathBuf::from(h).join(".netrc"))
+            })
+        else {
+            return Ok(Netrc::default());
+        };
+
+        match read_to_string(&path) {
+            Ok(input) => Ok(Netrc::parse(&input)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Netrc::default()),
+            Err(e) => Err(Error::generic(format!(
+                "Failed to read {}: {}",
+                path.display(),
+                e
+            ))),
+        }
+    }
+
+    pub fn parse(input: &str) -> Netrc {
+        let mut netrc = Netrc::default();
+
+        // The entry that's being parsed, with `Some(None)` being the default
+        // entry.
+        let mut entry: Option<Option<String>> = None;
+        let mut login = Login::default();
+        let mut in_macro = false;
+
+        for line in input.lines() {
+            // Macro definitions end at the first empty line.
+            if in_macro {
+                in_macro = !line.trim().is_empty();
+                continue;
+            }
+
+            let mut tokens = line.split_whitespace();
+
+            while let Some(token) = tokens.next() {
+                match token {
+                    "machine" | "default" => {
+                        if let Some(entry) = entry.take() {
+                            netrc.add(entry, login);
+                        }
+
+                        login = Login::default();
+                        entry = Some(if token == "machine" {
+                            Some(tokens.next().unwrap_or("").to_lowercase())
+                        } else {
+                            None
+                        });
+                    }
+                    "login" => {
+                        login.login = tokens.next().unwrap_or("").to_string()
+                    }
+                    "password" => {
+                        login.password = tokens.next().unwrap_or("").to_string()
+                    }
+                    "account" => {
+                        tokens.next();
+                    }
+                    "macdef" => {
+                        in_macro = true;
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if let Some(entry) = entry {
+            netrc.add(entry, login);
+        }
+
+        netrc
+    }
+
+    /// Returns the login defined for the given host.
+    pub fn login(&self, host: &str) -> Option<&Login> {
+        self.machines
+            .iter()
+            .find(|(name, _)| name == host)
+            .map(|(_, login)| login)
+    }
+
+    /// Returns the login to use for hosts without a login of their own.
+    pub fn default_login(&self) -> Option<&Login> {
+        self.default.as_ref()
+    }
+
+    fn add(&mut self, machine: Option<String>, login: Login) {
+        match machine {
+            Some(name) => self.machines.push((name, login)),
+            None => self.default = Some(login),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn login(login: &str, password: &str) -> Login {
+        Login {
+            login: login.to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let netrc = Netrc::parse(
+            "
+machine example.com login alice password secret
+machine Other.com
+  login bob
+  account foo
+  password hunter2
+
+macdef init
+machine evil.com login eve password nope
+
+default login anonymous password guest
+",
+        );
+
+        assert_eq!(netrc.login("example.com"), Some(&login("alice", "secret")));
+        assert_eq!(netrc.login("other.com"), Some(&login("bob", "hunter2")));
+        assert_eq!(netrc.login("evil.com"), None);
+        assert_eq!(netrc.login("foo.com"), None);
+        assert_eq!(netrc.default_login(), Some(&login("anonymous", "guest")));
+    }
+
+    #[test]
+    fn test_parse_without_default() {
+        let netrc = Netrc::parse("machine a.com login a password b");
+
+        assert_eq!(netrc.login("a.com"), Some(&login("a", "b")));
+        assert_eq!(netrc.login("b.com"), None);
+        assert_eq!(netrc.default_login(), None);
+        assert_eq!(Netrc::parse("").login("a.com"), None);
+    }
+}
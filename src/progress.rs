@@ -0,0 +1,250 @@
+use std::io::{self, IsTerminal, Read, Write};
+use std::time::{Duration, Instant};
+
+/// The time between updates when writing to a terminal.
+const TTY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The time between updates when not writing to a terminal, such as when
+/// the output is redirected to a log file.
+const PLAIN_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Copy, Clone)]
+enum Unit {
+    Bytes,
+    Files,
+}
+
+/// A progress indicator written to STDERR.
+///
+/// When STDERR is a terminal, a single line is updated in place. Otherwise a
+/// new line is written periodically. Operations that finish quickly don't
+/// produce any output.
+pub struct Progress {
+    label: String,
+    unit: Unit,
+    current: u64,
+    total: Option<u64>,
+
+    /// The value of `current` when the operation started, such as when
+    /// resuming a download.
+    initial: u64,
+    started: Instant,
+    drawn: Option<Instant>,
+    tty: bool,
+}
+
+impl Progress {
+    /// Returns a progress indicator for an operation measured in bytes.
+    pub fn bytes(label: &str, current: u64, total: Option<u64>) -> Progress {
+        Progress::new(label, Unit::Bytes, current, total)
+    }
+
+    /// Returns a progress indicator that counts files.
+    pub fn files(label: &str) -> Progress {
+        Progress::new(label, Unit::Files, 0, None)
+    }
+
+    fn new(
+        label: &str,
+        unit: Unit,
+        current: u64,
+        total: Option<u64>,
+    ) -> Progress {
+        Progress {
+            label: label.to_string(),
+            unit,
+            current,
+            total,
+            initial: current,
+            started: Instant::now(),
+            drawn: None,
+            tty: io::stderr().is_terminal(),
+        }
+    }
+
+    pub fn advance(&mut self, amount: u64) {
+        self.current += amount;
+
+        let interval = if self.tty {
+            TTY_INTERVAL
+        } else {
+            PLAIN_INTERVAL
+        };
+        let last = self.drawn.unwrap_or(self.started);
+
+        if last.elapsed() >= interval {
+            self.draw();
+        }
+    }
+
+    /// Writes the final state, if any progress was written before.
+    pub fn finish(&mut self) {
+        if self.drawn.is_none() {
+            return;
+        }
+
+        self.draw();
+
+        if self.tty {
+            eprintln!();
+        }
+    }
+
+    fn draw(&mut self) {
+        let message = self.message(self.started.elapsed());
+
+        if self.tty {
+            eprint!("\r\x1b[K\x1b[1minfo:\x1b[0m {}", message);
+            let _ = io::stderr().flush();
+        } else {
+            info!("{}", message);
+        }
+
+        self.drawn = Some(Instant::now());
+    }
+
+    fn message(&self, elapsed: Duration) -> String {
+        let mut message = format!("{}: ", self.label);
+
+        match self.unit {
+            Unit::Files => {
+                message.push_str(&format!("{} files", self.current));
+                return message;
+            }
+            Unit::Bytes => message.push_str(&format_bytes(self.current)),
+        }
+
+        if let Some(total) = self.total.filter(|&t| t > 0) {
+            message.push_str(&format!(
+                " / {} ({}%)",
+                format_bytes(total),
+                self.current.min(total) * 100 / total
+            ));
+        }
+
+        let secs = elapsed.as_secs_f64();
+        let rate = if secs > 0.0 {
+            (self.current - self.initial) as f64 / secs
+        } else {
+            0.0
+        };
+
+        if rate >= 1.0 {
+            message.push_str(&format!(", {}/s", format_bytes(rate as u64)));
+
+            if let Some(total) = self.total.filter(|&t| t > self.current) {
+                let eta = (total - self.current) as f64 / rate;
+
+                message.push_str(&format!(
+                    ", ETA {}",
+                    format_duration(Duration::from_secs_f64(eta))
+                ));
+            }
+        }
+
+        message
+    }
+}
+
+/// A reader that reports the number of bytes read to a progress indicator.
+pub struct ProgressReader<R: Read> {
+    inner: R,
+    progress: Progress,
+}
+
+impl<R: Read> ProgressReader<R> {
+    pub fn new(inner: R, progress: Progress) -> Self {
+        ProgressReader { inner, progress }
+    }
+
+    pub fn finish(mut self) {
+        self.progress.finish();
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+
+        self.progress.advance(len as u64);
+        Ok(len)
+    }
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs % 3600 / 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1024), "1.0 KiB");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024), "5.0 MiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(5)), "0:05");
+        assert_eq!(format_duration(Duration::from_secs(65)), "1:05");
+        assert_eq!(format_duration(Duration::from_secs(3725)), "1:02:05");
+    }
+
+    #[test]
+    fn test_message() {
+        let mut bytes = Progress::bytes("Downloading", 0, Some(4096));
+        let mut resumed = Progress::bytes("Downloading", 1024, None);
+        let mut files = Progress::files("Extracting");
+
+        bytes.current = 1024;
+        resumed.current = 2048;
+        files.current = 3;
+
+        assert_eq!(
+            bytes.message(Duration::from_secs(2)),
+            "Downloading: 1.0 KiB / 4.0 KiB (25%), 512 B/s, ETA 0:06"
+        );
+        assert_eq!(
+            resumed.message(Duration::from_secs(1)),
+            "Downloading: 2.0 KiB, 1.0 KiB/s"
+        );
+        assert_eq!(
+            bytes.message(Duration::from_secs(0)),
+            "Downloading: 1.0 KiB / 4.0 KiB (25%)"
+        );
+        assert_eq!(
+            files.message(Duration::from_secs(1)),
+            "Extracting: 3 files"
+        );
+    }
+}
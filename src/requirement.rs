@@ -0,0 +1,464 @@
+use crate::alias;
+use crate::error::Error;
+use crate::version::{Identifier, Version};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// The operator of a single comparator in a requirement.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Operator {
+    /// `=1.2.3`, or just `1.2.3`.
+    Exact,
+
+    /// `>1.2.3`
+    Greater,
+
+    /// `>=1.2.3`
+    GreaterEqual,
+
+    /// `<1.2.3`
+    Less,
+
+    /// `<=1.2.3`
+    LessEqual,
+
+    /// `~1.2.3`: the version must be at least 1.2.3 and below 1.3.0.
+    Tilde,
+
+    /// `^1.2.3`: the version must be at least 1.2.3 and compatible with it,
+    /// meaning the left-most non-zero component must be the same.
+    Caret,
+
+    /// `*`: any version is allowed.
+    Wildcard,
+}
+
+/// A single operator and (partial) version, such as `>=1.2`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Comparator {
+    operator: Operator,
+
+    /// The version components specified, which may be fewer than three.
+    components: Vec<u64>,
+
+    /// The pre-release identifiers, only allowed if all three components
+    /// are specified.
+    pre: Vec<Identifier>,
+
+    /// The build metadata identifiers, which don't affect precedence and are
+    /// only used when the requirement is an exact version.
+    build: Vec<Identifier>,
+}
+
+impl Comparator {
+    fn parse(input: &str) -> Result<Comparator, String> {
+        let (operator, rest) = if input == "*" {
+            return Ok(Comparator {
+                operator: Operator::Wildcard,
+                components: Vec::new(),
+                pre: Vec::new(),
+                build: Vec::new(),
+            });
+        } else if let Some(rest) = input.strip_prefix(">=") {
+            (Operator::GreaterEqual, rest)
+        } else if let Some(rest) = input.strip_prefix("<=") {
+            (Operator::LessEqual, rest)
+        } else if let Some(rest) = input.strip_prefix('>') {
+            (Operator::Greater, rest)
+        } else if let Some(rest) = input.strip_prefix('<') {
+            (Operator::Less, rest)
+        } else if let Some(rest) = input.strip_prefix('=') {
+            (Operator::Exact, rest)
+        } else if let Some(rest) = input.strip_prefix('~') {
+            (Operator::Tilde, rest)
+        } else if let Some(rest) = input.strip_prefix('^') {
+            (Operator::Caret, rest)
+        } else {
+            (Operator::Exact, input)
+        };
+
+        let rest = rest.trim_start();
+        let rest = rest.strip_prefix('v').unwrap_or(rest);
+        let (rest, build) = match rest.split_once('+') {
+            Some((rest, build)) => (rest, Version::parse_build(build)?),
+            None => (rest, Vec::new()),
+        };
+        let (rest, pre) = match rest.split_once('-') {
+            Some((rest, pre)) => (rest, Version::parse_pre(pre)?),
+            None => (rest, Vec::new()),
+        };
+        let mut components = Vec::new();
+
+        for (index, chunk) in rest.split('.').enumerate() {
+            // A trailing wildcard such as `1.2.*` is the same as `1.2`.
+            if (chunk == "*" || chunk == "x") && index > 0 {
+                if rest.split('.').count() > index + 1 {
+                    return Err(format!(
+                        "a wildcard must be the last component of {:?}",
+                        input
+                    ));
+                }
+
+                break;
+            }
+
+            if index == 3 {
+                return Err(format!("{:?} has too many components", input));
+            }
+
+            let value = Version::parse_number(chunk).map_err(|e| {
+                format!(
+                    "the component {:?} of {:?} is invalid: {}",
+                    chunk, rest, e
+                )
+            })?;
+
+            components.push(value);
+        }
+
+        if !pre.is_empty() && components.len() != 3 {
+            return Err(format!(
+                "{:?} must specify all components when using a pre-release",
+                input
+            ));
+        }
+
+        if !build.is_empty() && components.len() != 3 {
+            return Err(format!(
+                "{:?} must specify all components when using build metadata",
+                input
+            ));
+        }
+
+        Ok(Comparator {
+            operator,
+            components,
+            pre,
+            build,
+        })
+    }
+
+    fn matches(&self, version: &Version) -> bool {
+        let actual = [version.major(), version.minor(), version.patch()];
+        let given = self.components.as_slice();
+        let prefix = &actual[0..given.len()];
+
+        // Fully specified versions are compared using their precedence, such
+        // that pre-release identifiers are taken into account. Partial versions
+        // only compare the components that are specified.
+        let order = if let [major, minor, patch] = *given {
+            version.precedence(
+                &Version::new(major, minor, patch).with_pre(self.pre.clone()),
+            )
+        } else {
+            prefix.cmp(given)
+        };
+
+        match self.operator {
+            Operator::Exact => order == Ordering::Equal,
+            Operator::Greater => order == Ordering::Greater,
+            Operator::GreaterEqual => order != Ordering::Less,
+            Operator::Less => order == Ordering::Less,
+            Operator::LessEqual => order != Ordering::Greater,
+            Operator::Tilde => {
+                let fixed = given.len().min(2);
+
+                order != Ordering::Less && actual[0..fixed] == given[0..fixed]
+            }
+            Operator::Caret => {
+                // The components up to and including the left-most non-zero
+                // component must be the same. If all components are zero,
+                // all the specified components must be the same.
+                let fixed = given
+                    .iter()
+                    .position(|&v| v != 0)
+                    .map(|i| i + 1)
+                    .unwrap_or(given.len());
+
+                order != Ordering::Less && actual[0..fixed] == given[0..fixed]
+            }
+            Operator::Wildcard => true,
+        }
+    }
+
+    /// Returns `true` if the comparator explicitly opts in to pre-releases of
+    /// the given version.
+    fn allows_prerelease_of(&self, version: &Version) -> bool {
+        !self.pre.is_empty()
+            && self.components
+                == [version.major(), version.minor(), version.patch()]
+    }
+}
+
+/// A requirement a version must meet, such as `^0.18` or `>=0.16, <0.19`.
+///
+/// A requirement consists of one or more comparators separated by a comma, and
+/// a version must match all of them. A partial version such as `0.18` matches
+/// all versions that start with the given components.
+///
+/// Pre-release versions only match if a comparator includes a pre-release for
+/// the same major, minor and patch version, such as `>=0.19.0-rc.1`.
+///
+/// The keyword `latest` is the same as `*`, meaning it matches the highest
+/// version that isn't a pre-release.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Requirement {
+    input: String,
+    comparators: Vec<Comparator>,
+}
+
+impl Requirement {
+    pub fn parse(input: &str) -> Result<Requirement, Error> {
+        let mut comparators = Vec::new();
+        let chunks = if input.trim() == "latest" { "*" } else { input };
+
+        for chunk in chunks.split(',').map(|c| c.trim()) {
+            let comparator = Comparator::parse(chunk).map_err(|e| {
+                Error::generic(format!(
+                    "The requirement {:?} is invalid: {}",
+                    input, e
+                ))
+            })?;
+
+            comparators.push(comparator);
+        }
+
+        Ok(Requirement {
+            input: input.trim().to_string(),
+            comparators,
+        })
+    }
+
+    /// Parses a requirement given as a command-line argument.
+    ///
+    /// If the input is the name of an existing alias, the requirement the alias
+    /// points to is returned.
+    pub fn parse_or_alias(input: &str) -> Result<Requirement, Error> {
+        match alias::get(input)? {
+            Some(value) => {
+                info!("Using the alias {} for {}", input, value);
+                Requirement::parse(&value)
+            }
+            None => Requirement::parse(input),
+        }
+    }
+
+    /// Returns the version if the requirement only allows a single, fully
+    /// specified version.
+    pub fn exact(&self) -> Option<Version> {
+        match self.comparators.as_slice() {
+            [Comparator {
+                operator: Operator::Exact,
+                components,
+                pre,
+                build,
+            }] => match *components.as_slice() {
+                [major, minor, patch] => Some(
+                    Version::new(major, minor, patch)
+                        .with_pre(pre.clone())
+                        .with_build(build.clone()),
+                ),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn matches(&self, version: &Version) -> bool {
+        if version.is_prerelease()
+            && !self
+                .comparators
+                .iter()
+                .any(|c| c.allows_prerelease_of(version))
+        {
+            return false;
+        }
+
+        self.comparators.iter().all(|c| c.matches(version))
+    }
+
+    /// Returns the highest version that meets the requirement.
+    pub fn select<'a, I: IntoIterator<Item = &'a Version>>(
+        &self,
+        versions: I,
+    ) -> Option<Version> {
+        versions
+            .into_iter()
+            .filter(|v| self.matches(v))
+            .max()
+            .cloned()
+    }
+
+    /// Returns the highest installed version that meets the requirement.
+    ///
+    /// If the requirement is an exact version, that version is returned as-is,
+    /// leaving it up to the caller to handle the version not being installed.
+    pub fn installed(&self) -> Result<Version, Error> {
+        if let Some(version) = self.exact() {
+            return Ok(version);
+        }
+
+        self.select(&Version::installed()?).ok_or_else(|| {
+            Error::generic(format!(
+                "No installed version meets the requirement {}",
+                self
+            ))
+        })
+    }
+
+    /// Returns the same result as `installed()`, and informs the user about the
+    /// version selected if the requirement isn't an exact version.
+    pub fn select_installed(&self) -> Result<Version, Error> {
+        let version = self.installed()?;
+
+        if self.exact().is_none() {
+            info!("Using version {} for the requirement {}", version, self);
+        }
+
+        Ok(version)
+    }
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(requirement: &str, version: &str) -> bool {
+        Requirement::parse(requirement)
+            .unwrap()
+            .matches(&Version::parse(version).unwrap())
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(Requirement::parse("1.2.3").is_ok());
+        assert!(Requirement::parse("^1.2").is_ok());
+        assert!(Requirement::parse(">= 1.2, < 2").is_ok());
+        assert!(Requirement::parse("1.*").is_ok());
+        assert!(Requirement::parse("*").is_ok());
+        assert!(Requirement::parse("^v0.18").is_ok());
+        assert!(Requirement::parse("latest").is_ok());
+        assert!(Requirement::parse("").is_err());
+        assert!(Requirement::parse("1.2.3.4").is_err());
+        assert!(Requirement::parse("1.*.3").is_err());
+        assert!(Requirement::parse("^a").is_err());
+        assert!(Requirement::parse(">=1.2,").is_err());
+        assert!(Requirement::parse("1.2-rc.1").is_err());
+        assert!(Requirement::parse("1.2.3-rc..1").is_err());
+        assert!(Requirement::parse("01.2.3").is_err());
+        assert!(Requirement::parse("^1.02").is_err());
+        assert!(Requirement::parse("1.2.3+").is_err());
+        assert!(Requirement::parse("1.2+abc").is_err());
+        assert!(Requirement::parse("1.2.3+build.01").is_ok());
+    }
+
+    #[test]
+    fn test_exact() {
+        let exact = Some(Version::new(1, 2, 3));
+
+        assert_eq!(Requirement::parse("1.2.3").unwrap().exact(), exact);
+        assert_eq!(Requirement::parse("=1.2.3").unwrap().exact(), exact);
+        assert_eq!(Requirement::parse("1.2").unwrap().exact(), None);
+        assert_eq!(Requirement::parse("^1.2.3").unwrap().exact(), None);
+        assert_eq!(
+            Requirement::parse("1.2.3-rc.1").unwrap().exact(),
+            Version::parse("1.2.3-rc.1").ok()
+        );
+        assert_eq!(
+            Requirement::parse("1.2.3+abc").unwrap().exact(),
+            Version::parse("1.2.3+abc").ok()
+        );
+    }
+
+    #[test]
+    fn test_matches_exact() {
+        assert!(matches("1.2.3", "1.2.3"));
+        assert!(!matches("1.2.3", "1.2.4"));
+        assert!(matches("1.2", "1.2.0"));
+        assert!(matches("1.2", "1.2.9"));
+        assert!(!matches("1.2", "1.3.0"));
+        assert!(matches("1", "1.9.9"));
+        assert!(!matches("1", "2.0.0"));
+        assert!(matches("1.2.*", "1.2.5"));
+    }
+
+    #[test]
+    fn test_matches_ranges() {
+        assert!(matches(">1.2.3", "1.2.4"));
+        assert!(!matches(">1.2.3", "1.2.3"));
+        assert!(!matches(">1.2", "1.2.9"));
+        assert!(matches(">1.2", "1.3.0"));
+        assert!(matches(">=1.2", "1.2.0"));
+        assert!(matches("<1.2", "1.1.9"));
+        assert!(!matches("<1.2", "1.2.0"));
+        assert!(matches("<=1.2", "1.2.9"));
+        assert!(matches(">=0.16, <0.19", "0.18.1"));
+        assert!(!matches(">=0.16, <0.19", "0.19.0"));
+        assert!(!matches(">=0.16, <0.19", "0.15.0"));
+        assert!(matches("*", "0.1.0"));
+        assert!(matches("latest", "0.1.0"));
+        assert!(!matches("latest", "0.2.0-rc.1"));
+    }
+
+    #[test]
+    fn test_matches_tilde() {
+        assert!(matches("~0.18.1", "0.18.1"));
+        assert!(matches("~0.18.1", "0.18.5"));
+        assert!(!matches("~0.18.1", "0.18.0"));
+        assert!(!matches("~0.18.1", "0.19.0"));
+        assert!(matches("~0.18", "0.18.0"));
+        assert!(matches("~1", "1.5.0"));
+        assert!(!matches("~1", "2.0.0"));
+    }
+
+    #[test]
+    fn test_matches_caret() {
+        assert!(matches("^1.2.3", "1.9.0"));
+        assert!(!matches("^1.2.3", "1.2.2"));
+        assert!(!matches("^1.2.3", "2.0.0"));
+        assert!(matches("^0.17", "0.17.4"));
+        assert!(!matches("^0.17", "0.18.0"));
+        assert!(matches("^0.0.3", "0.0.3"));
+        assert!(!matches("^0.0.3", "0.0.4"));
+        assert!(matches("^0.0", "0.0.7"));
+        assert!(!matches("^0.0", "0.1.0"));
+    }
+
+    #[test]
+    fn test_matches_prerelease() {
+        assert!(!matches("*", "0.19.0-rc.1"));
+        assert!(!matches("^0.19", "0.19.0-rc.1"));
+        assert!(!matches(">=0.18", "0.19.0-rc.1"));
+        assert!(matches(">=0.19.0-rc.1", "0.19.0-rc.1"));
+        assert!(matches(">=0.19.0-rc.1", "0.19.0-rc.2"));
+        assert!(matches(">=0.19.0-rc.1", "0.19.0"));
+        assert!(!matches(">=0.19.0-rc.1", "0.19.1-rc.1"));
+        assert!(matches("^0.19.0-rc.1", "0.19.3"));
+        assert!(matches("<0.19.0", "0.18.9"));
+        assert!(!matches("<0.19.0", "0.19.0-rc.1"));
+        assert!(matches("0.19.0-rc.1", "0.19.0-rc.1"));
+        assert!(matches("0.19.0-rc.1+abc", "0.19.0-rc.1"));
+        assert!(!matches("0.19.0-rc.1", "0.19.0-rc.2"));
+    }
+
+    #[test]
+    fn test_select() {
+        let versions = vec![
+            Version::new(0, 17, 0),
+            Version::new(0, 18, 1),
+            Version::new(0, 18, 0),
+            Version::new(0, 19, 0),
+        ];
+        let req = Requirement::parse("^0.18").unwrap();
+        let none = Requirement::parse("1").unwrap();
+
+        assert_eq!(req.select(&versions), Some(Version::new(0, 18, 1)));
+        assert_eq!(none.select(&versions), None);
+    }
+}
@@ -0,0 +1,371 @@
+use crate::alias;
+use crate::config::{
+    default_version_file, PROJECT_VERSION_FILE, VERSION_VARIABLES,
+};
+use crate::error::Error;
+use crate::requirement::Requirement;
+use crate::version::Version;
+use std::env;
+use std::fmt;
+use std::fs::read;
+use std::path::{Path, PathBuf};
+
+/// The place a version was obtained from.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Source {
+    /// The version is set using an environment variable.
+    Environment(&'static str),
+
+    /// The version is pinned using a version file in a project directory.
+    Project(PathBuf),
+
+    /// The version is the global default version.
+    Default(PathBuf),
+}
+
+impl Source {
+    /// Returns the path of the file the version is read from, if any.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Source::Environment(_) => None,
+            Source::Project(path) | Source::Default(path) => Some(path),
+        }
+    }
+
+    /// Returns a short name describing the source.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Source::Environment(_) => "environment",
+            Source::Project(_) => "local",
+            Source::Default(_) => "default",
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::Environment(name) => {
+                write!(f, "the {} environment variable", name)
+            }
+            Source::Project(path) | Source::Default(path) => {
+                write!(f, "{}", path.display())
+            }
+        }
+    }
+}
+
+/// A version along with the place it was obtained from.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Resolved {
+    /// The version to use, or `None` if the source specifies a requirement
+    /// that no installed version meets.
+    pub version: Option<Version>,
+
+    /// The version or requirement as specified by the source.
+    pub requirement: String,
+
+    pub source: Source,
+
+    /// The name of the alias used to obtain the version, if any.
+    pub alias: Option<String>,
+}
+
+impl Resolved {
+    /// Returns the version to use, or an error if no installed version meets
+    /// the requirement.
+    pub fn installed(&self) -> Result<&Version, Error> {
+        self.version.as_ref().ok_or_else(|| {
+            Error::generic(format!(
+                "No installed version meets the requirement {} set by {}",
+                self.requirement,
+                self.origin()
+            ))
+        })
+    }
+
+    /// Returns a description of where the version is set.
+    pub fn origin(&self) -> String {
+        match &self.alias {
+            Some(alias) => format!("the alias {} in {}", alias, self.source),
+            None => self.source.to_string(),
+        }
+    }
+}
+
+/// Resolves the version to use for the current working directory, for
+/// marking the active version when listing versions.
+///
+/// Listing versions shouldn't fail because of an invalid version file or
+/// environment variable, so errors are reported as a warning instead.
+pub fn active() -> Option<Resolved> {
+    resolve().unwrap_or_else(|e| {
+        warn!("The active version couldn't be determined: {}", e);
+        None
+    })
+}
+
+/// Resolves the version to use for the current working directory.
+///
+/// The version is determined using the first of the following that is present:
+///
+/// 1. The IVM_VERSION or INKO_VERSION environment variable
+/// 2. A project version file in the current working directory or any of its
+///    parent directories
+/// 3. The global default version
+///
+/// The environment variables and project version files may specify a version,
+/// a requirement or the name of an alias. A requirement that no installed
+/// version meets doesn't produce an error, instead the version of the returned
+/// value is set to `None`.
+pub fn resolve() -> Result<Option<Resolved>, Error> {
+    let cwd = env::current_dir().map_err(|e| {
+        Error::generic(format!(
+            "The current working directory couldn't be determined: {}",
+            e
+        ))
+    })?;
+
+    resolve_with(
+        |name| env::var(name).ok(),
+        &cwd,
+        &default_version_file()?,
+        alias::get,
+        Version::installed,
+    )
+}
+
+fn resolve_with<V, A, I>(
+    variable: V,
+    directory: &Path,
+    default: &Path,
+    aliases: A,
+    installed: I,
+) -> Result<Option<Resolved>, Error>
+where
+    V: Fn(&str) -> Option<String>,
+    A: Fn(&str) -> Result<Option<String>, Error>,
+    I: Fn() -> Result<Vec<Version>, Error>,
+{
+    for name in VERSION_VARIABLES {
+        let Some(value) = variable(name).filter(|v| !v.trim().is_empty())
+        else {
+            continue;
+        };
+
+        let (requirement, alias) = parse(&value, &aliases).map_err(|e| {
+            Error::generic(format!("The variable {} is invalid: {}", name, e))
+        })?;
+
+        return Ok(Some(Resolved {
+            version: select(&requirement, &installed)?,
+            requirement: requirement.to_string(),
+            source: Source::Environment(name),
+            alias,
+        }));
+    }
+
+    if let Some(path) = find_project_file(directory) {
+        let data = read(&path).map_err(|e| {
+            Error::generic(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        let (requirement, alias) =
+            parse(&String::from_utf8_lossy(&data), &aliases).map_err(|e| {
+                Error::generic(format!(
+                    "Failed to parse {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+
+        return Ok(Some(Resolved {
+            version: select(&requirement, &installed)?,
+            requirement: requirement.to_string(),
+            source: Source::Project(path),
+            alias,
+        }));
+    }
+
+    if !default.is_file() {
+        return Ok(None);
+    }
+
+    let version = Version::read(default)?;
+
+    Ok(Some(Resolved {
+        requirement: version.to_string(),
+        version: Some(version),
+        source: Source::Default(default.to_path_buf()),
+        alias: None,
+    }))
+}
+
+/// Returns the project version file that applies to the given directory.
+pub fn find_project_file(directory: &Path) -> Option<PathBuf> {
+    directory
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_VERSION_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Parses a version, requirement or alias name, returning the requirement
+/// along with the name of the alias (if any).
+///
+/// The `aliases` argument is used to look up the value of an alias.
+fn parse<A: Fn(&str) -> Result<Option<String>, Error>>(
+    input: &str,
+    aliases: A,
+) -> Result<(Requirement, Option<String>), Error> {
+    let input = input.trim();
+
+    match aliases(input)? {
+        Some(value) => {
+            Ok((Requirement::parse(&value)?, Some(input.to_string())))
+        }
+        None => Ok((Requirement::parse(input)?, None)),
+    }
+}
+
+/// Returns the version to use for a requirement.
+///
+/// Exact versions are returned as-is, leaving it up to the caller to handle
+/// the version not being installed.
+fn select<I: Fn() -> Result<Vec<Version>, Error>>(
+    requirement: &Requirement,
+    installed: I,
+) -> Result<Option<Version>, Error> {
+    match requirement.exact() {
+        Some(version) => Ok(Some(version)),
+        None => Ok(requirement.select(&installed()?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, remove_dir_all, write};
+    use std::process;
+
+    fn installed() -> Result<Vec<Version>, Error> {
+        Ok(vec![Version::new(0, 19, 0), Version::new(0, 19, 1)])
+    }
+
+    fn aliases(name: &str) -> Result<Option<String>, Error> {
+        Ok((name == "stable").then(|| "0.19.0".to_string()))
+    }
+
+    #[test]
+    fn test_find_project_file() {
+        let root = env::temp_dir()
+            .join(format!("ivm-test-find-project-file-{}", process::id()));
+        let nested = root.join("a").join("b");
+        let file = root.join(PROJECT_VERSION_FILE);
+
+        create_dir_all(&nested).unwrap();
+
+        assert_eq!(find_project_file(&nested), None);
+
+        write(&file, "1.2.3").unwrap();
+
+        assert_eq!(find_project_file(&nested), Some(file.clone()));
+        assert_eq!(find_project_file(&root), Some(file.clone()));
+        assert_eq!(
+            parse(&std::fs::read_to_string(&file).unwrap(), aliases),
+            Ok((Requirement::parse("1.2.3").unwrap(), None))
+        );
+
+        remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_with() {
+        let root = env::temp_dir()
+            .join(format!("ivm-test-resolve-with-{}", process::id()));
+        let project = root.join("project");
+        let project_file = project.join(PROJECT_VERSION_FILE);
+        let default = root.join("version");
+        let no_vars = |_: &str| None;
+        let vars = |name: &str| match name {
+            "IVM_VERSION" => Some("0.18.0".to_string()),
+            "INKO_VERSION" => Some("0.17.0".to_string()),
+            _ => None,
+        };
+        let resolve = |vars: &dyn Fn(&str) -> Option<String>| {
+            resolve_with(vars, &project, &default, aliases, installed).unwrap()
+        };
+
+        create_dir_all(&project).unwrap();
+
+        assert_eq!(resolve(&no_vars), None);
+
+        write(&default, "0.16.0").unwrap();
+
+        assert_eq!(
+            resolve(&no_vars),
+            Some(Resolved {
+                version: Some(Version::new(0, 16, 0)),
+                requirement: "0.16.0".to_string(),
+                source: Source::Default(default.clone()),
+                alias: None,
+            })
+        );
+
+        write(&project_file, "^0.19").unwrap();
+
+        assert_eq!(
+            resolve(&no_vars),
+            Some(Resolved {
+                version: Some(Version::new(0, 19, 1)),
+                requirement: "^0.19".to_string(),
+                source: Source::Project(project_file.clone()),
+                alias: None,
+            })
+        );
+
+        let resolved = resolve(&vars).unwrap();
+
+        assert_eq!(resolved.version, Some(Version::new(0, 18, 0)));
+        assert_eq!(resolved.source, Source::Environment("IVM_VERSION"));
+        assert_eq!(
+            resolve(&|name: &str| vars(name).filter(|_| name != "IVM_VERSION"))
+                .unwrap()
+                .source,
+            Source::Environment("INKO_VERSION")
+        );
+
+        // A requirement that isn't met isn't an error.
+        write(&project_file, "^0.20").unwrap();
+
+        let resolved = resolve(&no_vars).unwrap();
+
+        assert_eq!(resolved.version, None);
+        assert_eq!(
+            resolved.installed(),
+            Err(Error::generic(format!(
+                "No installed version meets the requirement ^0.20 set by {}",
+                project_file.display()
+            )))
+        );
+
+        write(&project_file, "stable").unwrap();
+
+        assert_eq!(
+            resolve(&no_vars),
+            Some(Resolved {
+                version: Some(Version::new(0, 19, 0)),
+                requirement: "0.19.0".to_string(),
+                source: Source::Project(project_file.clone()),
+                alias: Some("stable".to_string()),
+            })
+        );
+
+        write(&project_file, "foo").unwrap();
+
+        assert!(
+            resolve_with(no_vars, &project, &default, aliases, installed)
+                .is_err()
+        );
+
+        remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,684 @@
+use crate::config::config_file;
+use crate::error::Error;
+use crate::netrc::Netrc;
+use getopts::{Matches, Options};
+use std::env;
+use std::fs::read_to_string;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// The base URL to download the manifest and release archives from, if no
+/// mirrors are configured.
+pub const DEFAULT_MIRROR: &str = "https://releases.inko-lang.org";
+
+/// The environment variable to use for overwriting the configured mirrors.
+const MIRROR_VARIABLE: &str = "IVM_MIRROR";
+
+/// The environment variable to use for overwriting the configured token.
+const TOKEN_VARIABLE: &str = "IVM_TOKEN";
+
+/// The environment variable to use for enabling offline mode.
+const OFFLINE_VARIABLE: &str = "IVM_OFFLINE";
+
+/// Settings that control how ivm behaves.
+///
+/// Settings are read from the configuration file, which consists of lines in
+/// the format `NAME = VALUE`. Empty lines and lines starting with a `#` are
+/// ignored. For example:
+///
+/// ```text
+/// # The key to verify signatures with. If no keys are configured, the key
+/// # embedded in ivm is used. If there's no such key either, --insecure must be
+/// # used.
+/// public_key = RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3
+///
+/// # The mirror to download from, followed by a fallback mirror.
+/// mirror = https://mirror.example.com/inko
+/// mirror = https://releases.inko-lang.org
+///
+/// # Timeouts in seconds, with 0 disabling the timeout.
+/// connect_timeout = 10
+/// read_timeout = 30
+/// timeout = 0
+///
+/// # The number of times to try a request before giving up.
+/// attempts = 3
+///
+/// # The PEM file containing the root certificates to trust, instead of the
+/// # bundled certificates.
+/// ca_bundle = /etc/ssl/certs/corporate.pem
+///
+/// # A PEM file containing the client certificate to present, and optionally
+/// # the PEM file containing its private key if the certificate file doesn't
+/// # contain it.
+/// client_certificate = /home/alice/.config/ivm/client.pem
+/// client_key = /home/alice/.config/ivm/client.key
+///
+/// # The bearer token to send to the configured mirrors.
+/// token = hunter2
+/// ```
+///
+/// Some settings can also be overwritten using environment variables or
+/// command-line options. Proxies are configured using the `HTTPS_PROXY`,
+/// `HTTP_PROXY` and `NO_PROXY` environment variables.
+///
+/// Credentials for mirrors are read from the netrc file (`~/.netrc` or the path
+/// in the `NETRC` variable), or taken from the `token` setting or the
+/// `IVM_TOKEN` variable.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Settings {
+    /// If set to `true`, signatures aren't verified and archives without a
+    /// checksum are allowed.
+    pub insecure: bool,
+
+    /// If set to `true`, only data that is already downloaded is used and no
+    /// network requests are performed.
+    pub offline: bool,
+
+    /// The minisign public keys to verify signatures with. If empty, the key
+    /// embedded in the executable is used, if there is one.
+    pub public_keys: Vec<String>,
+
+    /// The base URLs of the mirrors to download data from, in the order in
+    /// which to try them. A mirror can also be a local directory or `file://`
+    /// URL.
+    pub mirrors: Vec<String>,
+
+    /// The timeouts to apply to network requests.
+    pub timeouts: Timeouts,
+
+    /// The number of times to try a network request, if it fails due to a
+    /// transient error.
+    pub attempts: u32,
+
+    /// The proxies to send requests through.
+    pub proxies: Proxies,
+
+    /// The path to a PEM file containing the root certificates to trust.
+    pub ca_bundle: Option<PathBuf>,
+
+    /// The path to a PEM file containing the client certificate chain.
+    pub client_certificate: Option<PathBuf>,
+
+    /// The path to a PEM file containing the private key of the client
+    /// certificate. If not set, the key is read from the certificate file.
+    pub client_key: Option<PathBuf>,
+
+    /// The bearer token to send to the hosts of the configured mirrors.
+    pub token: Option<String>,
+
+    /// The logins to use for the hosts found in the netrc file.
+    pub netrc: Netrc,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            insecure: false,
+            offline: false,
+            public_keys: Vec::new(),
+            mirrors: Vec::new(),
+            timeouts: Timeouts::default(),
+            attempts: 3,
+            proxies: Proxies::default(),
+            ca_bundle: None,
+            client_certificate: None,
+            client_key: None,
+            token: None,
+            netrc: Netrc::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Loads the settings from the configuration file, if it exists.
+    pub fn load() -> Result<Settings, Error> {
+        let path = config_file()?;
+
+        let mut settings = if path.is_file() {
+            let input = read_to_string(&path).map_err(|e| {
+                Error::generic(format!(
+                    "Failed to read {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+
+            Settings::parse(&input).map_err(|e| {
+                Error::generic(format!(
+                    "Failed to parse {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?
+        } else {
+            Settings::default()
+        };
+
+        if let Some(value) = env::var(MIRROR_VARIABLE)
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+        {
+            settings.mirrors = parse_mirrors(&value);
+        }
+
+        if let Some(value) = env::var(TOKEN_VARIABLE)
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+        {
+            settings.token = Some(value.trim().to_string());
+        }
+
+        settings.proxies = Proxies::from_env();
+        settings.netrc = Netrc::load()?;
+
+        if env::var(OFFLINE_VARIABLE).is_ok_and(|v| is_enabled(&v)) {
+            settings.offline = true;
+        }
+
+        Ok(settings)
+    }
+
+    pub fn parse(input: &str) -> Result<Settings, String> {
+        let mut settings = Settings::default();
+
+        for (index, line) in input.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (name, value) = line
+                .split_once('=')
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .ok_or_else(|| {
+                    format!("line {} is missing a \"=\"", index + 1)
+                })?;
+
+            if value.is_empty() {
+                return Err(format!("line {} is missing a value", index + 1));
+            }
+
+            match name {
+                "public_key" => settings.public_keys.push(value.to_string()),
+                "mirror" => settings.mirrors.push(mirror_url(value)),
+                "connect_timeout" | "read_timeout" | "timeout" => {
+                    let timeout = parse_timeout(value)
+                        .map_err(|e| format!("line {}: {}", index + 1, e))?;
+
+                    match name {
+                        "connect_timeout" => {
+                            settings.timeouts.connect = timeout
+                        }
+                        "read_timeout" => settings.timeouts.read = timeout,
+                        _ => settings.timeouts.total = timeout,
+                    }
+                }
+                "attempts" => {
+                    settings.attempts = parse_attempts(value)
+                        .map_err(|e| format!("line {}: {}", index + 1, e))?;
+                }
+                "ca_bundle" => settings.ca_bundle = Some(value.into()),
+                "client_certificate" => {
+                    settings.client_certificate = Some(value.into())
+                }
+                "client_key" => settings.client_key = Some(value.into()),
+                "token" => settings.token = Some(value.to_string()),
+                _ => {
+                    return Err(format!(
+                        "line {} contains the unknown setting {:?}",
+                        index + 1,
+                        name
+                    ));
+                }
+            }
+        }
+
+        Ok(settings)
+    }
+
+    /// Applies the options added using [`options()`] to the settings.
+    pub fn apply(&mut self, matches: &Matches) -> Result<(), Error> {
+        if matches.opt_present("insecure") {
+            info!(
+                "Signatures won't be verified and checksums are optional, as \
+                --insecure is specified"
+            );
+            self.insecure = true;
+        }
+
+        let timeouts = [
+            ("connect-timeout", &mut self.timeouts.connect),
+            ("read-timeout", &mut self.timeouts.read),
+            ("timeout", &mut self.timeouts.total),
+        ];
+
+        for (name, timeout) in timeouts {
+            if let Some(value) = matches.opt_str(name) {
+                *timeout = parse_timeout(&value).map_err(|e| {
+                    Error::generic(format!("--{}: {}", name, e))
+                })?;
+            }
+        }
+
+        if let Some(value) = matches.opt_str("attempts") {
+            self.attempts = parse_attempts(&value)
+                .map_err(|e| Error::generic(format!("--attempts: {}", e)))?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns `true` if the host is the host of one of the configured
+    /// mirrors.
+    pub fn is_mirror_host(&self, host: &str) -> bool {
+        self.mirrors
+            .iter()
+            .filter_map(|m| m.split_once("://"))
+            .any(|(_, rest)| self::host(rest) == host)
+    }
+
+    /// Returns the base URLs of the mirrors to use, in the order in which to
+    /// try them.
+    pub fn mirrors(&self) -> Vec<&str> {
+        if self.mirrors.is_empty() {
+            vec![DEFAULT_MIRROR]
+        } else {
+            self.mirrors.iter().map(|s| s.as_str()).collect()
+        }
+    }
+}
+
+/// Timeouts to apply to network requests.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Timeouts {
+    /// The time to wait for a connection to be established.
+    pub connect: Option<Duration>,
+
+    /// The time to wait for data to arrive, reset whenever data is received.
+    pub read: Option<Duration>,
+
+    /// The maximum time a request, including reading its body, may take.
+    pub total: Option<Duration>,
+}
+
+impl Timeouts {
+    /// Returns a copy of the timeouts with the total time limited to the
+    /// given deadline.
+    pub fn with_deadline(self, deadline: Duration) -> Timeouts {
+        let total = self.total.map_or(deadline, |t| t.min(deadline));
+
+        Timeouts {
+            total: Some(total),
+            ..self
+        }
+    }
+}
+
+impl Default for Timeouts {
+    fn default() -> Timeouts {
+        // Downloading a source archive may take a while, so by default we
+        // only limit the time spent waiting for data and not the total time.
+        Timeouts {
+            connect: Some(Duration::from_secs(10)),
+            read: Some(Duration::from_secs(30)),
+            total: None,
+        }
+    }
+}
+
+/// The proxies to use, as specified using environment variables.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Proxies {
+    /// The proxy to use for HTTP requests.
+    pub http: Option<String>,
+
+    /// The proxy to use for HTTPS requests.
+    pub https: Option<String>,
+
+    /// The hosts for which no proxy is used.
+    pub no_proxy: Vec<String>,
+}
+
+impl Proxies {
+    /// Returns the proxies specified using the `HTTPS_PROXY`, `HTTP_PROXY`
+    /// and `NO_PROXY` variables, or their lowercase equivalents.
+    pub fn from_env() -> Proxies {
+        let var = |name: &str| {
+            env::var(name)
+                .or_else(|_| env::var(name.to_lowercase()))
+                .ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+
+        Proxies {
+            http: var("HTTP_PROXY"),
+            https: var("HTTPS_PROXY"),
+            no_proxy: var("NO_PROXY")
+                .map(|v| parse_no_proxy(&v))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Returns the proxy to use for the given URL, if any.
+    pub fn for_url(&self, url: &str) -> Option<&str> {
+        let (scheme, rest) = url.split_once("://")?;
+        let proxy = match scheme.to_lowercase().as_str() {
+            "https" => self.https.as_deref(),
+            "http" => self.http.as_deref(),
+            _ => None,
+        }?;
+
+        if self.bypass(&host(rest)) {
+            None
+        } else {
+            Some(proxy)
+        }
+    }
+
+    fn bypass(&self, host: &str) -> bool {
+        self.no_proxy.iter().any(|pattern| {
+            pattern == "*"
+                || host == pattern
+                || host
+                    .strip_suffix(pattern.as_str())
+                    .is_some_and(|prefix| prefix.ends_with('.'))
+        })
+    }
+}
+
+/// Adds the command-line options for overwriting network related settings.
+pub fn options(options: &mut Options) {
+    options.optflag(
+        "",
+        "insecure",
+        "Don't verify signatures or require checksums",
+    );
+    options.optopt(
+        "",
+        "connect-timeout",
+        "The time to wait for a connection, in seconds",
+        "SECS",
+    );
+    options.optopt(
+        "",
+        "read-timeout",
+        "The time to wait for data to arrive, in seconds",
+        "SECS",
+    );
+    options.optopt(
+        "",
+        "timeout",
+        "The maximum time a download may take, in seconds",
+        "SECS",
+    );
+    options.optopt(
+        "",
+        "attempts",
+        "The number of times to try a download",
+        "NUM",
+    );
+}
+
+/// Parses a timeout in seconds, with a timeout of zero disabling it.
+fn parse_timeout(value: &str) -> Result<Option<Duration>, String> {
+    match value.parse::<u64>() {
+        Ok(0) => Ok(None),
+        Ok(secs) => Ok(Some(Duration::from_secs(secs))),
+        Err(_) => Err(format!("the timeout {:?} is invalid", value)),
+    }
+}
+
+fn parse_attempts(value: &str) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(num) if num > 0 => Ok(num),
+        _ => Err(format!("the number of attempts {:?} is invalid", value)),
+    }
+}
+
+/// Parses a comma separated list of mirrors, as used by the `IVM_MIRROR`
+/// environment variable.
+fn parse_mirrors(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .map(mirror_url)
+        .collect()
+}
+
+/// Returns `true` if the value of a boolean environment variable enables the
+/// corresponding option.
+fn is_enabled(value: &str) -> bool {
+    !matches!(value.trim(), "" | "0" | "false" | "no")
+}
+
+/// Parses the value of the `NO_PROXY` variable.
+///
+/// Entries such as `example.com`, `.example.com` and `*.example.com` all match
+/// `example.com` and its subdomains.
+fn parse_no_proxy(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|v| v.trim().trim_start_matches("*.").trim_start_matches('.'))
+        .filter(|v| !v.is_empty())
+        .map(|v| v.to_lowercase())
+        .collect()
+}
+
+/// Returns the lowercase host of a URL without its scheme, excluding any
+/// credentials and port.
+pub fn host(url: &str) -> String {
+    let authority = url.split(['/', '?', '#']).next().unwrap_or("");
+    let authority = authority.rsplit_once('@').map_or(authority, |(_, h)| h);
+    let host = if let Some(rest) = authority.strip_prefix('[') {
+        rest.split(']').next().unwrap_or(rest)
+    } else {
+        authority.split(':').next().unwrap_or(authority)
+    };
+
+    host.to_lowercase()
+}
+
+fn mirror_url(value: &str) -> String {
+    value.trim_end_matches('/').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let input = "
+# A comment
+public_key = foo
+  public_key=bar
+";
+
+        assert_eq!(
+            Settings::parse(input),
+            Ok(Settings {
+                insecure: false,
+                public_keys: vec!["foo".to_string(), "bar".to_string()],
+                mirrors: Vec::new(),
+                ..Settings::default()
+            })
+        );
+        assert_eq!(Settings::parse(""), Ok(Settings::default()));
+        assert!(Settings::parse("public_key").is_err());
+        assert!(Settings::parse("foo = bar").is_err());
+        assert!(Settings::parse("mirror =").is_err());
+    }
+
+    #[test]
+    fn test_mirrors() {
+        let settings =
+            Settings::parse("mirror = https://a/\nmirror = https://b").unwrap();
+
+        assert_eq!(settings.mirrors(), vec!["https://a", "https://b"]);
+        assert_eq!(Settings::default().mirrors(), vec![DEFAULT_MIRROR]);
+    }
+
+    #[test]
+    fn test_parse_timeouts() {
+        let settings = Settings::parse(
+            "connect_timeout = 5\nread_timeout = 0\ntimeout = 60",
+        )
+        .unwrap();
+
+        assert_eq!(
+            settings.timeouts,
+            Timeouts {
+                connect: Some(Duration::from_secs(5)),
+                read: None,
+                total: Some(Duration::from_secs(60)),
+            }
+        );
+        assert!(Settings::parse("timeout = -1").is_err());
+        assert!(Settings::parse("timeout = 1s").is_err());
+    }
+
+    #[test]
+    fn test_parse_attempts() {
+        assert_eq!(Settings::default().attempts, 3);
+        assert_eq!(Settings::parse("attempts = 5").unwrap().attempts, 5);
+        assert!(Settings::parse("attempts = 0").is_err());
+        assert!(Settings::parse("attempts = foo").is_err());
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut opts = Options::new();
+        let mut settings = Settings::default();
+
+        options(&mut opts);
+
+        let args = [
+            "--insecure",
+            "--timeout",
+            "60",
+            "--read-timeout",
+            "0",
+            "--attempts",
+            "1",
+        ];
+        let matches = opts.parse(args).unwrap();
+
+        settings.apply(&matches).unwrap();
+
+        assert!(settings.insecure);
+        assert_eq!(settings.timeouts.total, Some(Duration::from_secs(60)));
+        assert_eq!(settings.timeouts.read, None);
+        assert_eq!(settings.timeouts.connect, Some(Duration::from_secs(10)));
+        assert_eq!(settings.attempts, 1);
+        assert!(settings
+            .apply(&opts.parse(["--timeout", "foo"]).unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_with_deadline() {
+        let secs = Duration::from_secs;
+        let timeouts = Timeouts::default();
+
+        assert_eq!(timeouts.with_deadline(secs(10)).total, Some(secs(10)));
+        assert_eq!(
+            Timeouts {
+                total: Some(secs(5)),
+                ..timeouts
+            }
+            .with_deadline(secs(10))
+            .total,
+            Some(secs(5))
+        );
+    }
+
+    #[test]
+    fn test_parse_mirrors() {
+        assert_eq!(
+            parse_mirrors("https://a/, https://b,,"),
+            vec!["https://a".to_string(), "https://b".to_string()]
+        );
+        assert!(parse_mirrors(" ").is_empty());
+    }
+
+    #[test]
+    fn test_is_enabled() {
+        assert!(is_enabled("1"));
+        assert!(is_enabled("true"));
+        assert!(!is_enabled(""));
+        assert!(!is_enabled("0"));
+        assert!(!is_enabled("false"));
+        assert!(!is_enabled("no"));
+    }
+
+    #[test]
+    fn test_parse_tls() {
+        let settings = Settings::parse(
+            "ca_bundle = /ca.pem\nclient_certificate = /a.pem\nclient_key = /a.key",
+        )
+        .unwrap();
+
+        assert_eq!(settings.ca_bundle, Some(PathBuf::from("/ca.pem")));
+        assert_eq!(settings.client_certificate, Some(PathBuf::from("/a.pem")));
+        assert_eq!(settings.client_key, Some(PathBuf::from("/a.key")));
+    }
+
+    #[test]
+    fn test_proxies_for_url() {
+        let proxies = Proxies {
+            http: Some("http://proxy:80".to_string()),
+            https: Some("http://proxy:443".to_string()),
+            no_proxy: parse_no_proxy("localhost, .internal.com,*.corp"),
+        };
+
+        assert_eq!(
+            proxies.for_url("https://a.com/x"),
+            Some("http://proxy:443")
+        );
+        assert_eq!(proxies.for_url("HTTP://a.com"), Some("http://proxy:80"));
+        assert_eq!(proxies.for_url("http://localhost:80/x"), None);
+        assert_eq!(proxies.for_url("https://internal.com"), None);
+        assert_eq!(proxies.for_url("https://a.internal.com"), None);
+        assert_eq!(proxies.for_url("https://u:p@x.corp/"), None);
+        assert_eq!(
+            proxies.for_url("https://notinternal.com"),
+            Some("http://proxy:443")
+        );
+        assert_eq!(proxies.for_url("/tmp/foo"), None);
+        assert_eq!(Proxies::default().for_url("https://a.com"), None);
+
+        let all = Proxies {
+            no_proxy: vec!["*".to_string()],
+            ..proxies
+        };
+
+        assert_eq!(all.for_url("https://a.com"), None);
+    }
+
+    #[test]
+    fn test_is_mirror_host() {
+        let settings = Settings::parse(
+            "mirror = https://a.com/inko\nmirror = /tmp/inko\ntoken = foo",
+        )
+        .unwrap();
+
+        assert_eq!(settings.token, Some("foo".to_string()));
+        assert!(settings.is_mirror_host("a.com"));
+        assert!(!settings.is_mirror_host("b.com"));
+        assert!(!settings.is_mirror_host(""));
+        assert!(!Settings::default().is_mirror_host("releases.inko-lang.org"));
+    }
+
+    #[test]
+    fn test_host() {
+        assert_eq!(host("example.com"), "example.com");
+        assert_eq!(host("Example.com:8080/foo"), "example.com");
+        assert_eq!(host("user:pass@example.com/foo"), "example.com");
+        assert_eq!(host("[::1]:8080/foo"), "::1");
+        assert_eq!(host("example.com?a=b"), "example.com");
+    }
+}
@@ -0,0 +1,186 @@
+use crate::config::{bin_directory, install_directory, INKO_EXE};
+use crate::error::Error;
+use crate::resolver::resolve;
+use std::env;
+use std::ffi::OsStr;
+use std::fs::{read_link, remove_file};
+use std::path::Path;
+use std::process::Command;
+
+#[cfg(unix)]
+use std::os::unix::fs::symlink;
+
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+
+#[cfg(windows)]
+use std::os::windows::fs::symlink_file as symlink;
+
+/// Returns `true` if ivm is invoked through the shim.
+///
+/// The shim in the bin directory is a symbolic link to the ivm executable, so
+/// we determine if we're running as a shim based on the name of the executable.
+pub fn invoked() -> bool {
+    env::args_os().next().is_some_and(|arg| is_shim(&arg))
+}
+
+fn is_shim(executable: &OsStr) -> bool {
+    Path::new(executable).file_stem() == Some(OsStr::new(INKO_EXE))
+}
+
+/// Runs the executable of the active version.
+pub fn run() -> Result<(), Error> {
+    let resolved = resolve()?.ok_or_else(|| {
+        Error::generic(
+            "No version is configured, use `ivm default` or `ivm local` to \
+            set one",
+        )
+    })?;
+
+    let version = resolved.installed()?;
+    let exe = install_directory()?
+        .join(version.to_string())
+        .join("bin")
+        .join(INKO_EXE);
+
+    if !exe.is_file() {
+        return Err(Error::generic(format!(
+            "Version {} is not installed, run `ivm install {}` to install it",
+            version, version
+        )));
+    }
+
+    let mut command = Command::new(&exe);
+
+    command.args(env::args_os().skip(1));
+    exec(command, &exe)
+}
+
+/// Creates or updates the shim in the bin directory.
+pub fn install() -> Result<(), Error> {
+    let ivm = env::current_exe().map_err(|e| {
+        Error::generic(format!(
+            "The path to the ivm executable couldn't be determined: {}",
+            e
+        ))
+    })?;
+
+    install_at(&ivm, &bin_directory()?.join(INKO_EXE))
+}
+
+/// Replaces the link to the executable of a specific version with the shim.
+///
+/// Older versions of ivm linked the executable of the default version into the
+/// bin directory, instead of using a shim. Such links ignore any project
+/// specific versions, so we replace them with the shim.
+pub fn migrate() -> Result<(), Error> {
+    let shim = bin_directory()?.join(INKO_EXE);
+
+    if is_version_link(&shim, &install_directory()?) {
+        info!("Replacing {} with the {} shim", shim.display(), INKO_EXE);
+        install()?;
+    }
+
+    Ok(())
+}
+
+fn install_at(ivm: &Path, shim: &Path) -> Result<(), Error> {
+    // The shim may be a symbolic link to a file that no longer exists, in
+    // which case exists() returns false.
+    if shim.is_symlink() || shim.exists() {
+        remove_file(shim)?;
+    }
+
+    symlink(ivm, shim).map_err(|e| {
+        Error::generic(format!("Failed to create {}: {}", shim.display(), e))
+    })?;
+
+    Ok(())
+}
+
+/// Returns `true` if the path is a link to a file in the install directory.
+fn is_version_link(path: &Path, install_directory: &Path) -> bool {
+    read_link(path).is_ok_and(|target| target.starts_with(install_directory))
+}
+
+#[cfg(unix)]
+fn exec(mut command: Command, exe: &Path) -> Result<(), Error> {
+    // exec() only returns if replacing the current process failed.
+    let error = command.exec();
+
+    Err(Error::generic(format!(
+        "Failed to run {}: {}",
+        exe.display(),
+        error
+    )))
+}
+
+#[cfg(not(unix))]
+fn exec(mut command: Command, exe: &Path) -> Result<(), Error> {
+    let status =
+        command
+            .spawn()
+            .and_then(|mut child| child.wait())
+            .map_err(|e| {
+                Error::generic(format!(
+                    "Failed to run {}: {}",
+                    exe.display(),
+                    e
+                ))
+            })?;
+
+    std::process::exit(status.code().unwrap_or(0));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, remove_dir_all, write};
+    use std::process;
+
+    #[test]
+    fn test_is_shim() {
+        assert!(is_shim(OsStr::new("inko")));
+        assert!(is_shim(OsStr::new("/home/alice/.local/bin/inko")));
+        assert!(!is_shim(OsStr::new("ivm")));
+        assert!(!is_shim(OsStr::new("/usr/bin/inkoc")));
+    }
+
+    #[test]
+    fn test_install_and_migrate() {
+        let root =
+            env::temp_dir().join(format!("ivm-test-shim-{}", process::id()));
+        let installs = root.join("installs");
+        let bin = root.join("bin");
+        let ivm = root.join("ivm");
+        let shim = bin.join(INKO_EXE);
+        let old = installs.join("0.18.1").join("bin").join(INKO_EXE);
+
+        create_dir_all(old.parent().unwrap()).unwrap();
+        create_dir_all(&bin).unwrap();
+        write(&ivm, "").unwrap();
+        write(&old, "").unwrap();
+
+        // A link to the executable of a specific version, as created by older
+        // versions of ivm.
+        symlink(&old, &shim).unwrap();
+        assert!(is_version_link(&shim, &installs));
+
+        install_at(&ivm, &shim).unwrap();
+        assert_eq!(read_link(&shim).unwrap(), ivm);
+        assert!(!is_version_link(&shim, &installs));
+
+        // Links to files that no longer exist are replaced as well.
+        remove_file(&ivm).unwrap();
+        install_at(&old, &shim).unwrap();
+        assert_eq!(read_link(&shim).unwrap(), old);
+
+        remove_file(&shim).unwrap();
+        write(&shim, "").unwrap();
+        assert!(!is_version_link(&shim, &installs));
+        install_at(&ivm, &shim).unwrap();
+        assert_eq!(read_link(&shim).unwrap(), ivm);
+
+        remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,360 @@
+use crate::error::Error;
+use crate::http;
+use crate::settings::Settings;
+use minisign_verify::{self as minisign, PublicKey, Signature, StreamVerifier};
+use std::io::{self, Read};
+
+/// The minisign public key of the key used for signing official releases.
+///
+/// No release key is published yet, so no key is embedded. Until one is, a key
+/// must be configured using the `public_key` setting, or signature verification
+/// must be disabled explicitly using `--insecure`.
+const PUBLIC_KEY: Option<&str> = None;
+
+/// Downloads and parses the detached signature of the file at the given URL.
+///
+/// The signature is expected to be located at the URL of the file with the
+/// `.minisig` extension added to it.
+pub fn download(url: &str, settings: &Settings) -> Result<Signature, Error> {
+    let url = format!("{}.minisig", url);
+
+    if !http::exists(&url, settings)? {
+        return Err(Error::generic(format!(
+            "The signature {} doesn't exist, use --insecure to skip \
+            signature verification",
+            http::redact(&url)
+        )));
+    }
+
+    let body = http::get_string(&url, settings)?;
+
+    parse(&body).map_err(|e| {
+        Error::generic(format!("Failed to parse {}: {}", http::redact(&url), e))
+    })
+}
+
+pub fn parse(input: &str) -> Result<Signature, Error> {
+    Signature::decode(input).map_err(|e| Error::generic(e.to_string()))
+}
+
+/// Returns the keys to verify signatures with, or `None` if signatures
+/// shouldn't be verified because `--insecure` is used.
+///
+/// If no keys are available, an error is returned instead of skipping the
+/// verification.
+pub fn keys(settings: &Settings) -> Result<Option<Keys>, Error> {
+    if settings.insecure {
+        return Ok(None);
+    }
+
+    let keys = Keys::new(settings)?;
+
+    if keys.keys.is_empty() {
+        return Err(Error::generic(
+            "No public keys are available to verify signatures with, add one \
+            using the public_key setting or use --insecure to skip signature \
+            verification",
+        ));
+    }
+
+    Ok(Some(keys))
+}
+
+/// The public keys used for verifying signatures.
+pub struct Keys {
+    keys: Vec<PublicKey>,
+}
+
+impl Keys {
+    /// Returns the keys to use for the given settings.
+    ///
+    /// If the settings don't specify any keys, the embedded key (if any) is
+    /// used.
+    pub fn new(settings: &Settings) -> Result<Keys, Error> {
+        let mut keys = Vec::new();
+
+        for key in &settings.public_keys {
+            keys.push(PublicKey::from_base64(key).map_err(|e| {
+                Error::generic(format!(
+                    "The public key {:?} is invalid: {}",
+                    key, e
+                ))
+            })?);
+        }
+
+        if let Some(key) = PUBLIC_KEY.filter(|_| keys.is_empty()) {
+            keys.push(PublicKey::from_base64(key).unwrap());
+        }
+
+        Ok(Keys { keys })
+    }
+
+    /// Verifies that the signature is valid for the given data, and that it
+    /// was produced for a file with the given name.
+    pub fn verify(
+        &self,
+        data: &[u8],
+        signature: &Signature,
+        name: &str,
+    ) -> Result<(), Error> {
+        let key = self.key_for(signature)?;
+
+        key.verify(data, signature, false).map_err(|_| invalid())?;
+        check_file(signature, name)
+    }
+
+    /// Returns a verifier to verify a signature against a stream of data.
+    ///
+    /// Once all data is processed, [`VerifyReader::finish()`] must be used to
+    /// also verify that the signature is produced for a file with the given
+    /// name.
+    pub fn stream<'a>(
+        &'a self,
+        signature: &'a Signature,
+    ) -> Result<StreamVerifier<'a>, Error> {
+        let key = self.key_for(signature)?;
+
+        key.verify_stream(signature).map_err(|e| match e {
+            minisign::Error::UnsupportedLegacyMode => Error::generic(
+                "Legacy signatures aren't supported, the data must be signed \
+                using a pre-hashed signature",
+            ),
+            _ => invalid(),
+        })
+    }
+
+    fn key_for(&self, signature: &Signature) -> Result<&PublicKey, Error> {
+        // minisign-verify doesn't expose the key IDs, so we instead look for
+        // the first key that doesn't produce a key ID mismatch.
+        self.keys
+            .iter()
+            .find(|key| {
+                !matches!(
+                    key.verify_stream(signature),
+                    Err(minisign::Error::UnexpectedKeyId)
+                )
+            })
+            .ok_or_else(|| {
+                Error::generic(
+                    "The data is signed using a key that isn't trusted",
+                )
+            })
+    }
+}
+
+/// Returns the value of a `KEY:VALUE` field in the trusted comment of a
+/// signature, such as the `timestamp` and `file` fields minisign adds by
+/// default.
+///
+/// The trusted comment is covered by the signature, so its value can only be
+/// trusted once the signature is verified.
+pub fn trusted_field<'a>(
+    signature: &'a Signature,
+    key: &str,
+) -> Option<&'a str> {
+    signature
+        .trusted_comment()
+        .split_whitespace()
+        .filter_map(|field| field.split_once(':'))
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| v)
+}
+
+/// Checks that the signature was produced for a file with the given name.
+///
+/// Without this check, a validly signed file could be served in place of
+/// another one, such as the archive of an older version.
+fn check_file(signature: &Signature, name: &str) -> Result<(), Error> {
+    match trusted_field(signature, "file") {
+        Some(file) if file == name => Ok(()),
+        Some(file) => Err(Error::generic(format!(
+            "The signature is produced for {}, not for {}",
+            file, name
+        ))),
+        None => Err(Error::generic(
+            "The trusted comment of the signature doesn't include the name \
+            of the signed file",
+        )),
+    }
+}
+
+/// A reader that verifies a signature against the data read through it.
+pub struct VerifyReader<'a, R: Read> {
+    inner: R,
+    verifier: Option<(StreamVerifier<'a>, &'a Signature, &'a str)>,
+}
+
+impl<'a, R: Read> VerifyReader<'a, R> {
+    /// Returns a new reader that verifies the data against the signature, and
+    /// checks that the signature is produced for a file with the given name.
+    pub fn new(
+        inner: R,
+        keys: &'a Keys,
+        signature: &'a Signature,
+        name: &'a str,
+    ) -> Result<Self, Error> {
+        let verifier = keys.stream(signature)?;
+
+        Ok(VerifyReader {
+            inner,
+            verifier: Some((verifier, signature, name)),
+        })
+    }
+
+    /// Returns a new reader that doesn't verify the data.
+    pub fn unverified(inner: R) -> Self {
+        VerifyReader {
+            inner,
+            verifier: None,
+        }
+    }
+
+    /// Reads any remaining data, then verifies the signature.
+    pub fn finish(mut self) -> Result<(), Error> {
+        io::copy(&mut self, &mut io::sink())?;
+
+        match self.verifier.as_mut() {
+            Some((verifier, signature, name)) => {
+                verifier.finalize().map_err(|_| invalid())?;
+                check_file(signature, name)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl<R: Read> Read for VerifyReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+
+        if let Some((verifier, _, _)) = self.verifier.as_mut() {
+            verifier.update(&buf[0..len]);
+        }
+
+        Ok(len)
+    }
+}
+
+fn invalid() -> Error {
+    Error::generic("The signature doesn't match the data")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str =
+        "RWQexs/l1eXUwCa82z/RWeq0xKqyiBLGJ7LsAkQvIQs3oNJdPJLvLE2H";
+    const OTHER_KEY: &str =
+        "RWSWt+5Dz7AxB1iBKlWmiz38j0CJrjPunfT2AZx7HLL/LEU1wBbU+m9I";
+    const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQexs/l1eXUwGVof8GPbDFum2qeO5R1Xl0PMiwkDKK9i9+w+PcR0WLXhQOqXAKpc6BLBVgmURbV0cXLOBXWVESHF0VQ9zOhogE=
+trusted comment: timestamp:0\tfile:hello.txt
+/feFp/Twl7yFPPQZ9j2OFhXy1ca1DJc4elSXj4/a3fveYnYUi37PUhyh85L7eZzfjduFZTj52BmhvT3iQflYCg==
+";
+
+    // The same data and key as SIGNATURE, but signed as a different file.
+    const OTHER_FILE: &str = "untrusted comment: signature from minisign secret key
+RUQexs/l1eXUwGVof8GPbDFum2qeO5R1Xl0PMiwkDKK9i9+w+PcR0WLXhQOqXAKpc6BLBVgmURbV0cXLOBXWVESHF0VQ9zOhogE=
+trusted comment: timestamp:0\tfile:other.txt
+hHZbHONUGRuUyJM2l6az+qNwBEjK0oNyRc1zMvr9X8h++W5CgRFYUfcF0zEKkEAfW1rfZLi5N8jpTM/fkc9sAg==
+";
+
+    fn settings(keys: &[&str]) -> Settings {
+        Settings {
+            public_keys: keys.iter().map(|k| k.to_string()).collect(),
+            ..Settings::default()
+        }
+    }
+
+    fn all_keys() -> Keys {
+        Keys::new(&settings(&[OTHER_KEY, KEY])).unwrap()
+    }
+
+    #[test]
+    fn test_keys() {
+        assert!(super::keys(&Settings::default()).is_err());
+        assert!(keys_for(&settings(&[KEY])).is_some());
+        assert!(keys_for(&Settings {
+            insecure: true,
+            ..Settings::default()
+        })
+        .is_none());
+        assert!(keys_for(&Settings {
+            insecure: true,
+            ..settings(&[KEY])
+        })
+        .is_none());
+        assert!(super::keys(&settings(&["foo"])).is_err());
+    }
+
+    fn keys_for(settings: &Settings) -> Option<Keys> {
+        super::keys(settings).unwrap()
+    }
+
+    #[test]
+    fn test_keys_new() {
+        assert_eq!(all_keys().keys.len(), 2);
+        assert!(Keys::new(&settings(&["foo"])).is_err());
+    }
+
+    #[test]
+    fn test_verify() {
+        let sig = parse(SIGNATURE).unwrap();
+        let other = parse(OTHER_FILE).unwrap();
+
+        assert!(all_keys().verify(b"hello", &sig, "hello.txt").is_ok());
+        assert!(all_keys().verify(b"hellO", &sig, "hello.txt").is_err());
+        assert!(all_keys().verify(b"hello", &sig, "other.txt").is_err());
+        assert!(all_keys().verify(b"hello", &other, "hello.txt").is_err());
+        assert!(all_keys().verify(b"hello", &other, "other.txt").is_ok());
+        assert!(Keys::new(&settings(&[OTHER_KEY]))
+            .unwrap()
+            .verify(b"hello", &sig, "hello.txt")
+            .is_err());
+    }
+
+    #[test]
+    fn test_verify_reader() {
+        let sig = parse(SIGNATURE).unwrap();
+        let other = parse(OTHER_FILE).unwrap();
+        let keys = all_keys();
+        let mut reader =
+            VerifyReader::new("hello".as_bytes(), &keys, &sig, "hello.txt")
+                .unwrap();
+        let mut buf = [0; 2];
+
+        reader.read_exact(&mut buf).unwrap();
+        assert!(reader.finish().is_ok());
+
+        let reader =
+            VerifyReader::new("hellO".as_bytes(), &keys, &sig, "hello.txt")
+                .unwrap();
+
+        assert!(reader.finish().is_err());
+
+        let reader =
+            VerifyReader::new("hello".as_bytes(), &keys, &other, "hello.txt")
+                .unwrap();
+
+        assert!(reader.finish().is_err());
+        assert!(VerifyReader::unverified("hellO".as_bytes())
+            .finish()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_trusted_field() {
+        let sig = parse(SIGNATURE).unwrap();
+
+        assert_eq!(trusted_field(&sig, "timestamp"), Some("0"));
+        assert_eq!(trusted_field(&sig, "file"), Some("hello.txt"));
+        assert_eq!(trusted_field(&sig, "foo"), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(parse(SIGNATURE).is_ok());
+        assert!(parse("foo").is_err());
+    }
+}
@@ -3,91 +3,403 @@ use crate::error::Error;
 use std::cmp::Ordering;
 use std::fmt;
 use std::fs::{read, read_dir};
+use std::path::Path;
 
+/// A single dot separated identifier in the pre-release part of a version.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Identifier {
+    Numeric(u64),
+    Alphanumeric(String),
+}
+
+impl Identifier {
+    /// Parses a list of dot separated identifiers, such as `rc.1`.
+    ///
+    /// If `numeric` is true, identifiers consisting of only digits are parsed
+    /// as numbers, and leading zeros are rejected.
+    fn parse_list(input: &str, numeric: bool) -> Result<Vec<Self>, String> {
+        let mut ids = Vec::new();
+
+        for id in input.split('.') {
+            if id.is_empty() {
+                return Err(format!(
+                    "{:?} contains an empty identifier",
+                    input
+                ));
+            }
+
+            if !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+                return Err(format!("{:?} contains invalid characters", input));
+            }
+
+            if numeric && id.chars().all(|c| c.is_ascii_digit()) {
+                if id.len() > 1 && id.starts_with('0') {
+                    return Err(format!(
+                        "the identifier {:?} has a leading zero",
+                        id
+                    ));
+                }
+
+                let value = id.parse::<u64>().map_err(|_| {
+                    format!("the identifier {:?} is too large", id)
+                })?;
+
+                ids.push(Identifier::Numeric(value));
+            } else {
+                ids.push(Identifier::Alphanumeric(id.to_string()));
+            }
+        }
+
+        Ok(ids)
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Identifier::Numeric(v) => write!(f, "{}", v),
+            Identifier::Alphanumeric(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+impl PartialOrd for Identifier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Identifier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Per the SemVer specification, numeric identifiers always have a lower
+        // precedence than alphanumeric identifiers.
+        match (self, other) {
+            (Identifier::Numeric(a), Identifier::Numeric(b)) => a.cmp(b),
+            (Identifier::Numeric(_), Identifier::Alphanumeric(_)) => {
+                Ordering::Less
+            }
+            (Identifier::Alphanumeric(_), Identifier::Numeric(_)) => {
+                Ordering::Greater
+            }
+            (Identifier::Alphanumeric(a), Identifier::Alphanumeric(b)) => {
+                a.cmp(b)
+            }
+        }
+    }
+}
+
+/// A version following the SemVer specification, such as `1.2.3`,
+/// `0.19.0-rc.1` or `0.19.0-nightly.20261001+abc123`.
 #[derive(PartialEq, Eq, Clone)]
 pub struct Version {
-    major: u8,
-    minor: u8,
-    patch: u8,
+    major: u64,
+    minor: u64,
+    patch: u64,
+
+    /// The pre-release identifiers, such as `rc` and `1` for `-rc.1`.
+    pre: Vec<Identifier>,
+
+    /// The build metadata identifiers, which don't affect precedence.
+    build: Vec<Identifier>,
 }
 
 impl Version {
-    pub fn default() -> Option<Self> {
-        default_version_file()
-            .ok()
-            .and_then(|path| {
-                if path.is_file() {
-                    read(path).ok()
-                } else {
-                    None
-                }
-            })
-            .and_then(|data| {
-                Self::parse(String::from_utf8_lossy(&data).trim()).ok()
-            })
+    /// Returns the global default version, if any.
+    pub fn default() -> Result<Option<Self>, Error> {
+        let path = default_version_file()?;
+
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        Self::read(&path).map(Some)
     }
 
-    pub fn latest_installed() -> Option<Self> {
-        let dir = install_directory().ok()?;
-        let mut latest = None;
+    /// Parses a version from a file, such as the default version file.
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let data = read(path).map_err(|e| {
+            Error::generic(format!("Failed to read {}: {}", path.display(), e))
+        })?;
 
-        for entry in read_dir(dir).ok()? {
+        Self::parse(&String::from_utf8_lossy(&data)).map_err(|e| {
+            Error::generic(format!("Failed to parse {}: {}", path.display(), e))
+        })
+    }
+
+    /// Returns the version specified in the contents of a `Cargo.toml` file.
+    ///
+    /// The version is taken from the `package` table, or from the
+    /// `workspace.package` table if the package doesn't specify a version
+    /// itself.
+    pub fn from_cargo_toml(input: &str) -> Option<Self> {
+        let mut section = "";
+        let mut package = None;
+        let mut workspace = None;
+
+        for line in input.lines().map(|l| l.trim()) {
+            if let Some(name) =
+                line.strip_prefix('[').and_then(|l| l.strip_suffix(']'))
+            {
+                section = name.trim();
+                continue;
+            }
+
+            let value = match line.split_once('=') {
+                Some((key, value)) if key.trim() == "version" => value.trim(),
+                _ => continue,
+            };
+            let value = match value.strip_prefix('"') {
+                Some(rest) => rest.split('"').next().unwrap_or(""),
+                _ => continue,
+            };
+
+            match section {
+                "package" => package = Some(value),
+                "workspace.package" => workspace = Some(value),
+                _ => {}
+            }
+        }
+
+        package.or(workspace).and_then(|v| Self::parse(v).ok())
+    }
+
+    /// Returns all installed versions, sorted from lowest to highest.
+    pub fn installed() -> Result<Vec<Self>, Error> {
+        let mut versions = Vec::new();
+
+        for entry in read_dir(install_directory()?)? {
             // If the version is invalid, it's probably because the entry is a
             // different (unexpected) directory. In that case we'll just ignore
             // it.
-            let version =
-                Version::parse(&entry.ok()?.file_name().to_string_lossy()).ok();
-
-            if version > latest {
-                latest = version;
+            if let Ok(version) =
+                Version::parse(&entry?.file_name().to_string_lossy())
+            {
+                versions.push(version);
             }
         }
 
-        latest
+        versions.sort();
+        Ok(versions)
     }
 
-    pub fn new(major: u8, minor: u8, patch: u8) -> Self {
+    pub fn new(major: u64, minor: u64, patch: u64) -> Self {
         Version {
             major,
             minor,
             patch,
+            pre: Vec::new(),
+            build: Vec::new(),
         }
     }
 
+    pub fn with_pre(mut self, pre: Vec<Identifier>) -> Self {
+        self.pre = pre;
+        self
+    }
+
+    pub fn with_build(mut self, build: Vec<Identifier>) -> Self {
+        self.build = build;
+        self
+    }
+
+    pub fn major(&self) -> u64 {
+        self.major
+    }
+
+    pub fn minor(&self) -> u64 {
+        self.minor
+    }
+
+    pub fn patch(&self) -> u64 {
+        self.patch
+    }
+
+    pub fn is_prerelease(&self) -> bool {
+        !self.pre.is_empty()
+    }
+
+    /// Parses the pre-release part of a version, such as `rc.1`.
+    pub fn parse_pre(input: &str) -> Result<Vec<Identifier>, String> {
+        Identifier::parse_list(input, true)
+    }
+
+    /// Parses the build metadata of a version, such as `build.5`.
+    pub fn parse_build(input: &str) -> Result<Vec<Identifier>, String> {
+        Identifier::parse_list(input, false)
+    }
+
+    /// Parses a single component of a version, such as the `2` in `1.2.3`.
+    pub fn parse_number(input: &str) -> Result<u64, String> {
+        let mut pos = 0;
+        let value = Self::parse_component(input, &mut pos)?;
+
+        match input[pos..].chars().next() {
+            Some(c) => {
+                Err(format!("unexpected {:?} at position {}", c, pos + 1))
+            }
+            None => Ok(value),
+        }
+    }
+
+    /// Parses a version such as `1.2.3` or `v0.19.0-rc.1`.
+    ///
+    /// Surrounding whitespace (including line endings) and a leading `v` are
+    /// ignored, but otherwise the input must be a valid SemVer version.
     pub fn parse(input: &str) -> Result<Version, Error> {
-        let mut chunks = input
-            .split('.')
-            .map(|chunk| {
-                chunk.parse::<u8>().map_err(|_| {
-                    Error::generic(format!(
-                        "The version {:?} is invalid",
-                        input
-                    ))
-                })
-            })
-            .take(3);
-
-        let major = chunks.next().unwrap_or(Ok(0))?;
-        let minor = chunks.next().unwrap_or(Ok(0))?;
-        let patch = chunks.next().unwrap_or(Ok(0))?;
+        let input = input.trim();
+        let invalid = |reason: String| {
+            Error::generic(format!(
+                "The version {:?} is invalid: {}",
+                input, reason
+            ))
+        };
+
+        let mut pos = if input.starts_with('v') { 1 } else { 0 };
+        let major = Self::parse_component(input, &mut pos).map_err(invalid)?;
+
+        Self::expect_dot(input, &mut pos).map_err(invalid)?;
+
+        let minor = Self::parse_component(input, &mut pos).map_err(invalid)?;
+
+        Self::expect_dot(input, &mut pos).map_err(invalid)?;
+
+        let patch = Self::parse_component(input, &mut pos).map_err(invalid)?;
+        let mut pre = Vec::new();
+        let mut build = Vec::new();
+
+        if let Some(rest) = input[pos..].strip_prefix('-') {
+            let start = pos + 1;
+            let len = rest.find('+').unwrap_or(rest.len());
+
+            pre = Self::parse_pre(&rest[0..len]).map_err(|e| {
+                invalid(format!("{} at position {}", e, start + 1))
+            })?;
+            pos = start + len;
+        }
+
+        if let Some(rest) = input[pos..].strip_prefix('+') {
+            let start = pos + 1;
+
+            build = Self::parse_build(rest).map_err(|e| {
+                invalid(format!("{} at position {}", e, start + 1))
+            })?;
+            pos = input.len();
+        }
+
+        if let Some(c) = input[pos..].chars().next() {
+            return Err(invalid(format!(
+                "unexpected {:?} at position {}",
+                c,
+                pos + 1
+            )));
+        }
 
         if major == 0 && minor == 0 && patch == 0 {
-            return Err(Error::generic("The version \"0.0.0\" is invalid"));
+            return Err(invalid("0.0.0 isn't a valid version".to_string()));
+        }
+
+        Ok(Version {
+            major,
+            minor,
+            patch,
+            pre,
+            build,
+        })
+    }
+
+    fn parse_component(input: &str, pos: &mut usize) -> Result<u64, String> {
+        let start = *pos;
+        let len = input[start..]
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(input.len() - start);
+        let digits = &input[start..start + len];
+
+        if digits.is_empty() {
+            return Err(match input[start..].chars().next() {
+                Some(c) => format!(
+                    "expected a number at position {}, found {:?}",
+                    start + 1,
+                    c
+                ),
+                None => {
+                    format!("expected a number at position {}", start + 1)
+                }
+            });
+        }
+
+        if digits.len() > 1 && digits.starts_with('0') {
+            return Err(format!("leading zero at position {}", start + 1));
+        }
+
+        *pos += len;
+        digits.parse::<u64>().map_err(|_| {
+            format!("the number at position {} is too large", start + 1)
+        })
+    }
+
+    fn expect_dot(input: &str, pos: &mut usize) -> Result<(), String> {
+        match input[*pos..].chars().next() {
+            Some('.') => {
+                *pos += 1;
+                Ok(())
+            }
+            Some(c) => Err(format!(
+                "expected \".\" at position {}, found {:?}",
+                *pos + 1,
+                c
+            )),
+            None => Err(format!("expected \".\" at position {}", *pos + 1)),
+        }
+    }
+
+    /// Compares two versions according to the SemVer precedence rules, meaning
+    /// build metadata is ignored.
+    pub fn precedence(&self, other: &Self) -> Ordering {
+        match self.major.cmp(&other.major) {
+            Ordering::Equal => {}
+            order => return order,
         }
 
-        Ok(Version::new(major, minor, patch))
+        match self.minor.cmp(&other.minor) {
+            Ordering::Equal => {}
+            order => return order,
+        }
+
+        match self.patch.cmp(&other.patch) {
+            Ordering::Equal => {}
+            order => return order,
+        }
+
+        // A pre-release version has a lower precedence than the same version
+        // without a pre-release.
+        match (self.pre.is_empty(), other.pre.is_empty()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => self.pre.cmp(&other.pre),
+        }
     }
 }
 
 impl fmt::Display for Version {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+
+        for (index, id) in self.pre.iter().enumerate() {
+            write!(f, "{}{}", if index == 0 { '-' } else { '.' }, id)?;
+        }
+
+        for (index, id) in self.build.iter().enumerate() {
+            write!(f, "{}{}", if index == 0 { '+' } else { '.' }, id)?;
+        }
+
+        Ok(())
     }
 }
 
 impl fmt::Debug for Version {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Version({}.{}.{})", self.major, self.minor, self.patch)
+        write!(f, "Version({})", self)
     }
 }
 
@@ -99,17 +411,12 @@ impl PartialOrd for Version {
 
 impl Ord for Version {
     fn cmp(&self, other: &Self) -> Ordering {
-        match self.major.cmp(&other.major) {
-            Ordering::Equal => {}
-            order => return order,
-        }
-
-        match self.minor.cmp(&other.minor) {
-            Ordering::Equal => {}
-            order => return order,
+        // Build metadata doesn't affect precedence, but we still take it into
+        // account here such that the ordering is consistent with equality.
+        match self.precedence(other) {
+            Ordering::Equal => self.build.cmp(&other.build),
+            order => order,
         }
-
-        self.patch.cmp(&other.patch)
     }
 }
 
@@ -119,8 +426,8 @@ mod tests {
 
     #[test]
     fn test_parse() {
-        assert_eq!(Version::parse("1"), Ok(Version::new(1, 0, 0)));
-        assert_eq!(Version::parse("1.1"), Ok(Version::new(1, 1, 0)));
+        assert!(Version::parse("1").is_err());
+        assert!(Version::parse("1.1").is_err());
         assert_eq!(Version::parse("1.0.0"), Ok(Version::new(1, 0, 0)));
         assert_eq!(Version::parse("1.0.1"), Ok(Version::new(1, 0, 1)));
         assert_eq!(Version::parse("1.1.1"), Ok(Version::new(1, 1, 1)));
@@ -128,6 +435,69 @@ mod tests {
         assert!(Version::parse("1.a.1").is_err());
         assert!(Version::parse("a.1.1").is_err());
         assert!(Version::parse("0.0.0").is_err());
+        assert_eq!(Version::parse("1.300.0"), Ok(Version::new(1, 300, 0)));
+        assert_eq!(
+            Version::parse("0.19.0-rc.1"),
+            Ok(Version::new(0, 19, 0).with_pre(vec![
+                Identifier::Alphanumeric("rc".to_string()),
+                Identifier::Numeric(1)
+            ]))
+        );
+        assert!(Version::parse("1.0.0-").is_err());
+        assert!(Version::parse("1.0.0-rc..1").is_err());
+        assert!(Version::parse("1.0.0-rc.01").is_err());
+        assert!(Version::parse("1.0.0-rc_1").is_err());
+        assert!(Version::parse("1.0.0+").is_err());
+        assert!(Version::parse("1.0.0+build.01").is_ok());
+    }
+
+    #[test]
+    fn test_parse_strict() {
+        assert_eq!(Version::parse("v1.2.3"), Ok(Version::new(1, 2, 3)));
+        assert_eq!(Version::parse(" 1.2.3\r\n"), Ok(Version::new(1, 2, 3)));
+        assert_eq!(Version::parse("1.2.3\n"), Ok(Version::new(1, 2, 3)));
+        assert!(Version::parse("vv1.2.3").is_err());
+        assert!(Version::parse("V1.2.3").is_err());
+        assert!(Version::parse("1.02.3").is_err());
+        assert!(Version::parse("1..3").is_err());
+        assert!(Version::parse("1.2.").is_err());
+        assert!(Version::parse("1.2.3 4").is_err());
+        assert!(Version::parse("").is_err());
+        assert_eq!(
+            Version::parse("1.2.3.4").map_err(|e| e.to_string()),
+            Err("The version \"1.2.3.4\" is invalid: unexpected '.' at \
+                position 6"
+                .to_string())
+        );
+        assert_eq!(
+            Version::parse("1.x.3").map_err(|e| e.to_string()),
+            Err("The version \"1.x.3\" is invalid: expected a number at \
+                position 3, found 'x'"
+                .to_string())
+        );
+        assert_eq!(
+            Version::parse("1.2.3-rc..1").map_err(|e| e.to_string()),
+            Err(
+                "The version \"1.2.3-rc..1\" is invalid: \"rc..1\" contains \
+                an empty identifier at position 7"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_display_round_trip() {
+        for input in [
+            "1.2.3",
+            "0.19.0-rc.1",
+            "0.19.0-nightly.20261001",
+            "1.0.0-alpha-1.beta",
+            "1.0.0+build.5",
+            "1.0.0-rc.1+sha.abc123",
+            "4294967296.0.0",
+        ] {
+            assert_eq!(Version::parse(input).unwrap().to_string(), input);
+        }
     }
 
     #[test]
@@ -140,6 +510,38 @@ mod tests {
         assert!(Version::new(0, 0, 1) > Version::new(0, 0, 0));
     }
 
+    #[test]
+    fn test_cmp_prerelease() {
+        let order = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "1.0.1-nightly.20261001",
+            "1.0.1-nightly.20261002",
+        ];
+
+        for pair in order.windows(2) {
+            let a = Version::parse(pair[0]).unwrap();
+            let b = Version::parse(pair[1]).unwrap();
+
+            assert!(a < b, "{} should be less than {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_cmp_build() {
+        let a = Version::parse("1.0.0+a").unwrap();
+        let b = Version::parse("1.0.0+b").unwrap();
+
+        assert_eq!(a.precedence(&b), Ordering::Equal);
+        assert_ne!(a, b);
+    }
+
     #[test]
     fn test_to_string() {
         assert_eq!(Version::new(1, 2, 3).to_string(), "1.2.3");
@@ -154,4 +556,48 @@ mod tests {
     fn test_debug() {
         assert_eq!(format!("{:?}", Version::new(1, 2, 3)), "Version(1.2.3)");
     }
+
+    #[test]
+    fn test_from_cargo_toml() {
+        let package = "
+[package]
+name = \"inko\"
+version = \"0.18.1\" # A comment
+
+[dependencies]
+foo = { version = \"1.0.0\" }
+";
+        let workspace = "
+[workspace]
+members = [\"compiler\"]
+
+[workspace.package]
+version = \"0.19.0\"
+
+[dependencies.foo]
+version = \"1.0.0\"
+";
+        let inherited = "
+[package]
+version.workspace = true
+
+[workspace.package]
+version = \"0.19.0\"
+";
+
+        assert_eq!(
+            Version::from_cargo_toml(package),
+            Some(Version::new(0, 18, 1))
+        );
+        assert_eq!(
+            Version::from_cargo_toml(workspace),
+            Some(Version::new(0, 19, 0))
+        );
+        assert_eq!(
+            Version::from_cargo_toml(inherited),
+            Some(Version::new(0, 19, 0))
+        );
+        assert_eq!(Version::from_cargo_toml("[package]\nname = \"a\""), None);
+        assert_eq!(Version::from_cargo_toml("version = \"1.0.0\""), None);
+    }
 }